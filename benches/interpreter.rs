@@ -0,0 +1,169 @@
+//! Instructions per second with a concrete bus type and through `dyn Bus`,
+//! and what the core's fn pointer opcode dispatch costs on the same
+//! instruction stream.
+//!
+//! Run with `cargo bench --bench interpreter`.
+use mos6502::{Bus, Cpu};
+use std::hint::black_box;
+use std::ops::{Index, IndexMut};
+use std::time::Instant;
+
+const INSTRUCTIONS: u32 = 20_000_000;
+
+struct Ram([u8; 0x10000]);
+
+impl Index<u16> for Ram {
+    type Output = u8;
+    fn index(&self, addr: u16) -> &u8 {
+        &self.0[addr as usize]
+    }
+}
+
+impl IndexMut<u16> for Ram {
+    fn index_mut(&mut self, addr: u16) -> &mut u8 {
+        &mut self.0[addr as usize]
+    }
+}
+
+// A nestest-like mix of loads, stores, ALU, read-modify-write, indexed and
+// indirect addressing, branches and subroutine calls.
+#[rustfmt::skip]
+const PROGRAM: [u8; 30] = [
+    0xA2, 0x00,       // $C000 LDX #$00
+    0xBD, 0x00, 0x03, // $C002 LDA $0300,X
+    0x69, 0x01,       //       ADC #$01
+    0x9D, 0x00, 0x04, //       STA $0400,X
+    0xE6, 0x10,       //       INC $10
+    0x20, 0x17, 0xC0, //       JSR $C017
+    0xE8,             //       INX
+    0xD0, 0xF0,       //       BNE $C002
+    0x4C, 0x00, 0xC0, //       JMP $C000
+    0xEA, 0xEA,       //       padding
+    0xB1, 0x20,       // $C017 LDA ($20),Y
+    0x45, 0x11,       //       EOR $11
+    0x0A,             //       ASL A
+    0x60,             //       RTS
+    0xEA,
+];
+
+fn setup() -> (Cpu, Box<Ram>) {
+    let mut ram = Box::new(Ram([0; 0x10000]));
+    ram.0[0xC000..0xC000 + PROGRAM.len()].copy_from_slice(&PROGRAM);
+    ram.0[0x20] = 0x00;
+    ram.0[0x21] = 0x05;
+    (Cpu::new(Some(0xC000)), ram)
+}
+
+fn report(name: &str, start: Instant) -> f64 {
+    let per_second = INSTRUCTIONS as f64 / start.elapsed().as_secs_f64();
+    println!("{:>8}: {:>7.2} M instructions/s", name, per_second / 1e6);
+    per_second
+}
+
+// Kept out of line and fed a black-boxed reference so the compiler cannot
+// see the concrete bus type and devirtualize the calls.
+#[inline(never)]
+fn run_dyn(cpu: &mut Cpu, bus: &mut dyn Bus) {
+    for _ in 0..INSTRUCTIONS {
+        black_box(cpu.run_instr(bus)).unwrap();
+    }
+}
+
+// The core makes one indirect call per instruction, through the `Op` it
+// decodes at fetch. These stand-ins time that call on its own: the same
+// work reached through a table of fn pointers or through a match.
+type Handler = fn(&mut u8, u8);
+
+fn add(acc: &mut u8, opcode: u8) {
+    *acc = acc.wrapping_add(opcode);
+}
+fn xor(acc: &mut u8, opcode: u8) {
+    *acc ^= opcode;
+}
+fn rotate(acc: &mut u8, _: u8) {
+    *acc = acc.rotate_left(1);
+}
+fn nop(_: &mut u8, _: u8) {}
+
+fn handler(opcode: u8) -> Handler {
+    match opcode >> 6 {
+        0 => add,
+        1 => xor,
+        2 => rotate,
+        _ => nop,
+    }
+}
+
+const STREAM: usize = 4096;
+
+// The opcodes the workload runs, in order.
+fn opcode_stream() -> [u8; STREAM] {
+    let (mut cpu, mut ram) = setup();
+    std::array::from_fn(|_| {
+        let opcode = ram.0[cpu.pc as usize];
+        cpu.run_instr(&mut *ram).unwrap();
+        opcode
+    })
+}
+
+#[inline(never)]
+fn dispatch_fn_pointer(stream: &[u8; STREAM], table: &[Handler; 256]) -> u8 {
+    let mut acc = 0;
+    for n in 0..INSTRUCTIONS as usize {
+        let opcode = stream[n % STREAM];
+        table[opcode as usize](&mut acc, opcode);
+    }
+    acc
+}
+
+#[inline(never)]
+fn dispatch_match(stream: &[u8; STREAM]) -> u8 {
+    let mut acc = 0;
+    for n in 0..INSTRUCTIONS as usize {
+        let opcode = stream[n % STREAM];
+        match opcode >> 6 {
+            0 => add(&mut acc, opcode),
+            1 => xor(&mut acc, opcode),
+            2 => rotate(&mut acc, opcode),
+            _ => nop(&mut acc, opcode),
+        }
+    }
+    acc
+}
+
+fn nanoseconds(per_second: f64) -> f64 {
+    1e9 / per_second
+}
+
+fn main() {
+    let (mut cpu, mut ram) = setup();
+    let start = Instant::now();
+    for _ in 0..INSTRUCTIONS {
+        black_box(cpu.run_instr(&mut *ram)).unwrap();
+    }
+    let generic = report("generic", start);
+
+    let (mut cpu, mut ram) = setup();
+    let start = Instant::now();
+    run_dyn(&mut cpu, black_box(&mut *ram as &mut dyn Bus));
+    let dynamic = report("dyn Bus", start);
+
+    println!("generic is {:.2}x the dyn path", generic / dynamic);
+
+    let stream = opcode_stream();
+    let table: [Handler; 256] = std::array::from_fn(|n| handler(n as u8));
+    let start = Instant::now();
+    black_box(dispatch_fn_pointer(black_box(&stream), black_box(&table)));
+    let fn_pointer = report("fn ptr", start);
+    let start = Instant::now();
+    black_box(dispatch_match(black_box(&stream)));
+    let matched = report("match", start);
+
+    println!(
+        "dispatch takes {:.2} ns through a fn pointer and {:.2} ns through a match, \
+         of the {:.2} ns per instruction",
+        nanoseconds(fn_pointer),
+        nanoseconds(matched),
+        nanoseconds(generic)
+    );
+}
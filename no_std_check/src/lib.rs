@@ -0,0 +1,45 @@
+//! Firmware-style use of the core: no std, no alloc, a fixed memory map.
+#![no_std]
+
+use core::panic::PanicInfo;
+use mos6502::{Bus, Cpu};
+
+// INC $10; JMP $FF00, with the RESET vector pointing at it.
+const ROM: [u8; 6] = [0xE6, 0x10, 0x4C, 0x00, 0xFF, 0x00];
+
+struct Board {
+    ram: [u8; 0x0800],
+}
+
+impl Bus for Board {
+    fn read(&mut self, addr: u16) -> u8 {
+        match addr {
+            0x0000..=0x07FF => self.ram[addr as usize],
+            0xFF00..=0xFF05 => ROM[addr as usize - 0xFF00],
+            0xFFFD => 0xFF,
+            _ => 0,
+        }
+    }
+    fn write(&mut self, addr: u16, val: u8) {
+        if addr < 0x0800 {
+            self.ram[addr as usize] = val;
+        }
+    }
+}
+
+/// Runs the ROM for `cycles` cycles and returns the counter it increments.
+#[no_mangle]
+pub extern "C" fn mos6502_run(cycles: u64) -> u8 {
+    let mut board = Board { ram: [0; 0x0800] };
+    let mut cpu = Cpu::new(None);
+    cpu.power_on(&mut board);
+    match cpu.run_cycles(&mut board, cycles) {
+        Ok(_) => board.ram[0x10],
+        Err(_) => 0xFF,
+    }
+}
+
+#[panic_handler]
+fn panic(_: &PanicInfo) -> ! {
+    loop {}
+}
@@ -0,0 +1,396 @@
+use super::{Bus, Cpu, Interrupt, Op};
+
+// Cycle sequencing for each addressing mode. `step` is the index of the
+// cycle being run, counting the opcode fetch as step 0.
+impl Cpu {
+    fn finish(&mut self) {
+        self.step = 0;
+    }
+    fn fetch_operand<B: Bus + ?Sized>(&mut self, bus: &mut B) -> u8 {
+        let val = bus.read(self.pc);
+        self.pc = self.pc.wrapping_add(1);
+        val
+    }
+    // Internal cycle: the 6502 drives the bus on every cycle and, with
+    // nothing better to do, re-reads the byte at PC.
+    fn internal<B: Bus + ?Sized>(&mut self, bus: &mut B) {
+        bus.read(self.pc);
+    }
+    fn stack_dummy_read<B: Bus + ?Sized>(&mut self, bus: &mut B) {
+        bus.read(self.sp as u16 + 0x100);
+    }
+    fn is_read(&self) -> bool {
+        matches!(self.current_instr, Op::Read(_))
+    }
+    // Operand access once `addr` holds the effective address. `n` counts
+    // the cycles spent in this phase.
+    fn access<B: Bus + ?Sized>(&mut self, bus: &mut B, n: u8) {
+        match self.current_instr {
+            Op::Read(f) => match n {
+                0 => {
+                    let m = bus.read(self.addr);
+                    f(self, m);
+                    if !self.decimal_cycle() {
+                        self.finish();
+                    }
+                }
+                // 65C02 decimal ADC/SBC spend a cycle on the BCD fixup
+                _ => {
+                    self.internal(bus);
+                    self.finish();
+                }
+            },
+            Op::Write(f) => {
+                let m = f(self);
+                bus.write(self.addr, m);
+                self.finish();
+            }
+            Op::Modify(f) => match n {
+                0 => self.data = bus.read(self.addr),
+                // the unmodified value is written back while the ALU works;
+                // the 65C02 reads it again instead
+                1 if self.variant.is_cmos() => {
+                    bus.read(self.addr);
+                }
+                1 => bus.write(self.addr, self.data),
+                _ => {
+                    let m = f(self, self.data);
+                    bus.write(self.addr, m);
+                    self.finish();
+                }
+            },
+            Op::Implied(f) => {
+                f(self);
+                self.finish();
+            }
+        }
+    }
+    pub(super) fn implied<B: Bus + ?Sized>(&mut self, bus: &mut B) {
+        self.internal(bus);
+        match self.current_instr {
+            Op::Implied(f) => f(self),
+            Op::Modify(f) => self.a = f(self, self.a),
+            _ => {}
+        }
+        self.finish();
+    }
+    pub(super) fn immediate<B: Bus + ?Sized>(&mut self, bus: &mut B, step: u8) {
+        if step == 1 {
+            self.addr = self.pc;
+            self.pc = self.pc.wrapping_add(1);
+        }
+        self.access(bus, step - 1);
+    }
+    pub(super) fn zero_page<B: Bus + ?Sized>(&mut self, bus: &mut B, step: u8) {
+        match step {
+            1 => self.addr = self.fetch_operand(bus) as u16,
+            n => self.access(bus, n - 2),
+        }
+    }
+    pub(super) fn zero_page_indexed<B: Bus + ?Sized>(&mut self, bus: &mut B, step: u8, index: u8) {
+        match step {
+            1 => self.addr = self.fetch_operand(bus) as u16,
+            2 => {
+                bus.read(self.addr);
+                self.addr = (self.addr as u8).wrapping_add(index) as u16;
+            }
+            n => self.access(bus, n - 3),
+        }
+    }
+    pub(super) fn absolute<B: Bus + ?Sized>(&mut self, bus: &mut B, step: u8) {
+        match step {
+            1 => self.addr = self.fetch_operand(bus) as u16,
+            2 => self.addr |= (self.fetch_operand(bus) as u16) << 8,
+            n => self.access(bus, n - 3),
+        }
+    }
+    pub(super) fn absolute_indexed<B: Bus + ?Sized>(&mut self, bus: &mut B, step: u8, index: u8) {
+        match step {
+            1 => self.base = self.fetch_operand(bus) as u16,
+            2 => {
+                self.base |= (self.fetch_operand(bus) as u16) << 8;
+                self.addr = self.base.wrapping_add(index as u16);
+            }
+            3 => self.index_fixup(bus),
+            n => self.access(bus, n - 4),
+        }
+    }
+    // The low byte has been indexed but the carry into the high byte is
+    // still pending, so this cycle reads from the un-carried address. Reads
+    // finish here unless the index carried; writes and read-modify-writes
+    // always pay for the fixup cycle. The 65C02 re-reads the last operand
+    // byte instead of touching the un-carried address.
+    fn index_fixup<B: Bus + ?Sized>(&mut self, bus: &mut B) {
+        let crossed = (self.base ^ self.addr) & 0xFF00 != 0;
+        if (self.is_read() || self.fast_rmw()) && !crossed {
+            self.access(bus, 0);
+            // the access phase has started a cycle early
+            if self.step != 0 {
+                self.step += 1;
+            }
+        } else if self.variant.is_cmos() {
+            bus.read(self.pc.wrapping_sub(1));
+        } else {
+            bus.read((self.base & 0xFF00) | (self.addr & 0x00FF));
+        }
+    }
+    pub(super) fn indexed_indirect_x<B: Bus + ?Sized>(&mut self, bus: &mut B, step: u8) {
+        match step {
+            1 => self.data = self.fetch_operand(bus),
+            2 => {
+                bus.read(self.data as u16);
+                self.data = self.data.wrapping_add(self.x);
+            }
+            3 => self.addr = bus.read(self.data as u16) as u16,
+            4 => self.addr |= (bus.read(self.data.wrapping_add(1) as u16) as u16) << 8,
+            n => self.access(bus, n - 5),
+        }
+    }
+    pub(super) fn indirect_indexed_y<B: Bus + ?Sized>(&mut self, bus: &mut B, step: u8) {
+        match step {
+            1 => self.data = self.fetch_operand(bus),
+            2 => self.base = bus.read(self.data as u16) as u16,
+            3 => {
+                self.base |= (bus.read(self.data.wrapping_add(1) as u16) as u16) << 8;
+                self.addr = self.base.wrapping_add(self.y as u16);
+            }
+            4 => self.index_fixup(bus),
+            n => self.access(bus, n - 5),
+        }
+    }
+    pub(super) fn zero_page_indirect<B: Bus + ?Sized>(&mut self, bus: &mut B, step: u8) {
+        match step {
+            1 => self.data = self.fetch_operand(bus),
+            2 => self.addr = bus.read(self.data as u16) as u16,
+            3 => self.addr |= (bus.read(self.data.wrapping_add(1) as u16) as u16) << 8,
+            n => self.access(bus, n - 4),
+        }
+    }
+    pub(super) fn relative<B: Bus + ?Sized>(&mut self, bus: &mut B, step: u8) {
+        match step {
+            1 => {
+                self.data = self.fetch_operand(bus);
+                if !self.branch_taken() {
+                    self.finish();
+                }
+            }
+            2 => {
+                self.internal(bus);
+                self.base = self.pc;
+                self.pc = self.pc.wrapping_add(self.data as i8 as u16);
+                if (self.base ^ self.pc) & 0xFF00 == 0 {
+                    // A taken branch that stays on its page does not poll
+                    // on this cycle: an interrupt that only just arrived
+                    // waits for the next instruction.
+                    if self.poll && !self.prev_poll {
+                        self.poll = false;
+                    }
+                    self.finish();
+                }
+            }
+            _ => {
+                bus.read((self.base & 0xFF00) | (self.pc & 0x00FF));
+                self.finish();
+            }
+        }
+    }
+    pub(super) fn jmp_absolute<B: Bus + ?Sized>(&mut self, bus: &mut B, step: u8) {
+        match step {
+            1 => self.addr = self.fetch_operand(bus) as u16,
+            _ => {
+                self.addr |= (bus.read(self.pc) as u16) << 8;
+                self.pc = self.addr;
+                self.finish();
+            }
+        }
+    }
+    // On NMOS parts JMP ($xxFF) fetches its high byte from $xx00: the
+    // pointer increment does not carry. The 65C02 fixes this at the cost of
+    // a cycle, which JMP (abs,X) also uses to add the index.
+    pub(super) fn jmp_indirect<B: Bus + ?Sized>(&mut self, bus: &mut B, step: u8, index: u8) {
+        let cmos = self.variant.is_cmos();
+        match (step, cmos) {
+            (1, _) => self.base = self.fetch_operand(bus) as u16,
+            (2, _) => self.base |= (self.fetch_operand(bus) as u16) << 8,
+            (3, true) => {
+                bus.read(self.pc.wrapping_sub(1));
+                self.base = self.base.wrapping_add(index as u16);
+            }
+            (3, false) | (4, true) => self.addr = bus.read(self.base) as u16,
+            _ => {
+                let hi = if cmos {
+                    self.base.wrapping_add(1)
+                } else {
+                    (self.base & 0xFF00) | (self.base as u8).wrapping_add(1) as u16
+                };
+                self.addr |= (bus.read(hi) as u16) << 8;
+                self.pc = self.addr;
+                self.finish();
+            }
+        }
+    }
+    pub(super) fn jsr<B: Bus + ?Sized>(&mut self, bus: &mut B, step: u8) {
+        match step {
+            1 => self.addr = self.fetch_operand(bus) as u16,
+            2 => self.stack_dummy_read(bus),
+            3 => self.StackPush(bus, (self.pc >> 8) as u8),
+            4 => self.StackPush(bus, self.pc as u8),
+            _ => {
+                self.addr |= (bus.read(self.pc) as u16) << 8;
+                self.pc = self.addr;
+                self.finish();
+            }
+        }
+    }
+    pub(super) fn rts<B: Bus + ?Sized>(&mut self, bus: &mut B, step: u8) {
+        match step {
+            1 => self.internal(bus),
+            2 => self.stack_dummy_read(bus),
+            3 => self.addr = self.StackPull(bus) as u16,
+            4 => self.addr |= (self.StackPull(bus) as u16) << 8,
+            _ => {
+                self.pc = self.addr;
+                self.internal(bus);
+                self.pc = self.pc.wrapping_add(1);
+                self.finish();
+            }
+        }
+    }
+    pub(super) fn rti<B: Bus + ?Sized>(&mut self, bus: &mut B, step: u8) {
+        match step {
+            1 => self.internal(bus),
+            2 => self.stack_dummy_read(bus),
+            3 => {
+                let p = self.StackPull(bus);
+                self.s.set((p | 0x20) & 0xEF);
+            }
+            4 => self.addr = self.StackPull(bus) as u16,
+            _ => {
+                self.addr |= (self.StackPull(bus) as u16) << 8;
+                self.pc = self.addr;
+                self.finish();
+            }
+        }
+    }
+    // BRK, IRQ and NMI share this sequence. BRK skips its signature byte
+    // and pushes P with B set; the hardware interrupts re-read PC instead
+    // and push B clear. The vector is only chosen while P is pushed, so an
+    // NMI that arrives before then hijacks the sequence and it continues
+    // at $FFFA with whatever B was pushed. The 65C02 does not let an NMI
+    // hijack BRK; it is taken after the BRK handler's first instruction.
+    // RESET runs the same sequence with the stack writes turned into reads.
+    fn brk_push<B: Bus + ?Sized>(&mut self, bus: &mut B, val: u8) {
+        if self.interrupt == Some(Interrupt::Reset) {
+            self.stack_dummy_read(bus);
+            self.sp = self.sp.wrapping_sub(1);
+        } else {
+            self.StackPush(bus, val);
+        }
+    }
+    pub(super) fn brk<B: Bus + ?Sized>(&mut self, bus: &mut B, step: u8) {
+        match step {
+            1 => {
+                if self.interrupt.is_some() {
+                    self.internal(bus);
+                } else {
+                    self.fetch_operand(bus);
+                }
+            }
+            2 => self.brk_push(bus, (self.pc >> 8) as u8),
+            3 => self.brk_push(bus, self.pc as u8),
+            4 => {
+                let hijack = match self.interrupt {
+                    Some(Interrupt::Reset) => false,
+                    Some(_) => true,
+                    None => !self.variant.is_cmos(),
+                };
+                self.base = if self.nmi_edge && hijack {
+                    self.nmi_edge = false;
+                    Interrupt::Nmi.vector()
+                } else {
+                    self.interrupt.map_or(0xFFFE, Interrupt::vector)
+                };
+                let p = if self.interrupt.is_some() {
+                    (self.s.get() | 0x20) & !0x10
+                } else {
+                    self.s.get() | 0x30
+                };
+                self.brk_push(bus, p);
+            }
+            5 => {
+                self.addr = bus.read(self.base) as u16;
+                self.s.set_interrupt(true);
+                if self.variant.is_cmos() {
+                    self.s.set_decimal(false);
+                }
+            }
+            _ => {
+                self.addr |= (bus.read(self.base + 1) as u16) << 8;
+                self.pc = self.addr;
+                self.interrupt = None;
+                self.finish();
+            }
+        }
+    }
+    pub(super) fn push<B: Bus + ?Sized>(&mut self, bus: &mut B, step: u8) {
+        match step {
+            1 => self.internal(bus),
+            _ => {
+                if let Op::Write(f) = self.current_instr {
+                    let m = f(self);
+                    self.StackPush(bus, m);
+                }
+                self.finish();
+            }
+        }
+    }
+    pub(super) fn pull<B: Bus + ?Sized>(&mut self, bus: &mut B, step: u8) {
+        match step {
+            1 => self.internal(bus),
+            2 => self.stack_dummy_read(bus),
+            _ => {
+                let m = self.StackPull(bus);
+                if let Op::Read(f) = self.current_instr {
+                    f(self, m);
+                }
+                self.finish();
+            }
+        }
+    }
+    // Undefined on the 65C02: fetches an absolute operand, then keeps the
+    // bus busy for five more cycles.
+    pub(super) fn nop8<B: Bus + ?Sized>(&mut self, bus: &mut B, step: u8) {
+        match step {
+            1 => self.addr = self.fetch_operand(bus) as u16,
+            2 => self.addr |= (self.fetch_operand(bus) as u16) << 8,
+            3..=6 => self.internal(bus),
+            _ => self.access(bus, 0),
+        }
+    }
+    pub(super) fn bit_branch<B: Bus + ?Sized>(&mut self, bus: &mut B, step: u8) {
+        match step {
+            1 => self.addr = self.fetch_operand(bus) as u16,
+            2 => self.data = bus.read(self.addr),
+            3 => {
+                bus.read(self.addr);
+            }
+            4 => {
+                let taken = self.bit_branch_taken(self.data);
+                self.data = self.fetch_operand(bus);
+                if !taken {
+                    self.finish();
+                }
+            }
+            n => self.relative(bus, n - 3),
+        }
+    }
+    pub(super) fn halt<B: Bus + ?Sized>(&mut self, bus: &mut B, step: u8) {
+        self.internal(bus);
+        if step == 2 {
+            if let Op::Implied(f) = self.current_instr {
+                f(self);
+            }
+            self.finish();
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use core::ops::IndexMut;
+
+/// Address space seen by the CPU.
+///
+/// Reads take `&mut self` so a device can react to being read, e.g. clear a
+/// status flag or acknowledge an interrupt.
+pub trait Bus {
+    fn read(&mut self, addr: u16) -> u8;
+    fn write(&mut self, addr: u16, val: u8);
+}
+
+/// Plain memories indexed by `u16` have no read side effects.
+impl<T: IndexMut<u16, Output = u8> + ?Sized> Bus for T {
+    fn read(&mut self, addr: u16) -> u8 {
+        self[addr]
+    }
+    fn write(&mut self, addr: u16, val: u8) {
+        self[addr] = val;
+    }
+}
+
+/// Bus access without side effects, for debuggers: `peek` must not
+/// acknowledge or clear anything and `poke` must not trigger devices.
+pub trait DebugBus: Bus {
+    fn peek(&self, addr: u16) -> u8;
+    fn poke(&mut self, addr: u16, val: u8);
+}
+
+impl<T: IndexMut<u16, Output = u8> + ?Sized> DebugBus for T {
+    fn peek(&self, addr: u16) -> u8 {
+        self[addr]
+    }
+    fn poke(&mut self, addr: u16, val: u8) {
+        self[addr] = val;
+    }
+}
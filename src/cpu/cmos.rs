@@ -0,0 +1,110 @@
+use super::{Cpu, Mode, Mode::*, Op, RunState, Variant};
+
+// 65C02 decoding. The CMOS parts keep the NMOS encoding for every
+// documented opcode and reuse the illegal slots for new instructions; the
+// rest are NOPs whose length and timing follow the WDC datasheet.
+impl Cpu {
+    pub(super) fn decode_cmos(&self) -> (Mode, Op) {
+        match self.instruction.get() {
+            0x04 => (ZeroPage, Op::Modify(Cpu::TSB)),
+            0x0C => (Absolute, Op::Modify(Cpu::TSB)),
+            0x14 => (ZeroPage, Op::Modify(Cpu::TRB)),
+            0x1C => (Absolute, Op::Modify(Cpu::TRB)),
+            // the column-2 JAM slots gain `(zp)` forms of the ALU opcodes
+            0x12 | 0x32 | 0x52 | 0x72 | 0x92 | 0xB2 | 0xD2 | 0xF2 => (ZeroPageIndirect, self.alu_op()),
+            0x1A => (Accumulator, Op::Modify(Cpu::INC)),
+            0x3A => (Accumulator, Op::Modify(Cpu::DEC)),
+            0x34 => (ZeroPageX, Op::Read(Cpu::BIT)),
+            0x3C => (AbsoluteX, Op::Read(Cpu::BIT)),
+            0x89 => (Immediate, Op::Read(Cpu::BIT_IMM)),
+            0x5A => (Push, Op::Write(Cpu::PHY)),
+            0x7A => (Pull, Op::Read(Cpu::PLY)),
+            0xDA => (Push, Op::Write(Cpu::PHX)),
+            0xFA => (Pull, Op::Read(Cpu::PLX)),
+            0x64 => (ZeroPage, Op::Write(Cpu::STZ)),
+            0x74 => (ZeroPageX, Op::Write(Cpu::STZ)),
+            0x9C => (Absolute, Op::Write(Cpu::STZ)),
+            0x9E => (AbsoluteX, Op::Write(Cpu::STZ)),
+            0x7C => (JmpIndX, Op::Implied(Cpu::NOP)),
+            0x80 => (Relative, Op::Implied(Cpu::NOP)),
+            0x02 | 0x22 | 0x42 | 0x62 | 0x82 | 0xC2 | 0xE2 => (Immediate, Op::Read(Cpu::IGN)),
+            0x44 => (ZeroPage, Op::Read(Cpu::IGN)),
+            0x54 | 0xD4 | 0xF4 => (ZeroPageX, Op::Read(Cpu::IGN)),
+            0x5C => (Nop8, Op::Read(Cpu::IGN)),
+            0xDC | 0xFC => (Absolute, Op::Read(Cpu::IGN)),
+            op if op & 0x0F == 0x07 && self.variant.has_bit_instructions() => {
+                let op = if op & 0x80 == 0 { Cpu::RMB } else { Cpu::SMB };
+                (ZeroPage, Op::Modify(op))
+            }
+            op if op & 0x0F == 0x0F && self.variant.has_bit_instructions() => {
+                (BitBranch, Op::Implied(Cpu::NOP))
+            }
+            0xCB if self.variant == Variant::W65C02S => (Halt, Op::Implied(Cpu::WAI)),
+            0xDB if self.variant == Variant::W65C02S => (Halt, Op::Implied(Cpu::STP)),
+            op if op & 0x03 == 0x03 => (Nop1, Op::Implied(Cpu::NOP)),
+            _ => self.decode_nmos(),
+        }
+    }
+    // The extra cycle ADC and SBC take in decimal mode.
+    pub(super) fn decimal_cycle(&self) -> bool {
+        let opcode = self.instruction.get();
+        let adder = (self.instruction.cc() == 1 || opcode & 0x1F == 0x12)
+            && matches!(self.instruction.aaa(), 3 | 7);
+        self.variant.is_cmos() && adder && self.s.get_decimal()
+    }
+    // Shifts and rotates on abs,X skip the fixup cycle when the index does
+    // not carry; INC and DEC always pay for it.
+    pub(super) fn fast_rmw(&self) -> bool {
+        self.variant.is_cmos() && matches!(self.instruction.get(), 0x1E | 0x3E | 0x5E | 0x7E)
+    }
+    // Bit number for RMB/SMB/BBR/BBS, encoded in the opcode's high nibble.
+    pub(super) fn opcode_bit(&self) -> u8 {
+        1 << ((self.instruction.get() >> 4) & 0x07)
+    }
+    // Whether BBRn/BBSn branches on the zero page value `m`.
+    pub(super) fn bit_branch_taken(&self, m: u8) -> bool {
+        let set = m & self.opcode_bit() != 0;
+        set == (self.instruction.get() & 0x80 != 0)
+    }
+    fn RMB(&mut self, m: u8) -> u8 {
+        m & !self.opcode_bit()
+    }
+    fn SMB(&mut self, m: u8) -> u8 {
+        m | self.opcode_bit()
+    }
+    fn WAI(&mut self) {
+        self.state = RunState::Waiting;
+    }
+    fn STP(&mut self) {
+        self.state = RunState::Stopped;
+    }
+    fn TSB(&mut self, m: u8) -> u8 {
+        self.s.set_zero(self.a & m == 0);
+        m | self.a
+    }
+    fn TRB(&mut self, m: u8) -> u8 {
+        self.s.set_zero(self.a & m == 0);
+        m & !self.a
+    }
+    // Immediate BIT has no memory operand to copy N and V from.
+    fn BIT_IMM(&mut self, m: u8) {
+        self.s.set_zero(self.a & m == 0);
+    }
+    fn STZ(&mut self) -> u8 {
+        0
+    }
+    fn PHX(&mut self) -> u8 {
+        self.x
+    }
+    fn PHY(&mut self) -> u8 {
+        self.y
+    }
+    fn PLX(&mut self, m: u8) {
+        self.x = m;
+        self.set_flags_z_n(m);
+    }
+    fn PLY(&mut self, m: u8) {
+        self.y = m;
+        self.set_flags_z_n(m);
+    }
+}
@@ -0,0 +1,89 @@
+use super::Cpu;
+
+// BCD arithmetic of the NMOS adder. N, V and Z come from intermediate
+// binary results rather than from the decimal value left in A, which is
+// what real chips do and what software detecting the CPU relies on. The
+// 65C02 fixes N and Z to reflect A and corrects SBC differently.
+impl Cpu {
+    pub(super) fn decimal_mode(&self) -> bool {
+        self.s.get_decimal() && self.variant.has_decimal_mode()
+    }
+    pub(super) fn adc_decimal(&mut self, m: u8) {
+        let a = self.a as u16;
+        let m = m as u16;
+        let c = self.s.get_carry() as u16;
+        let mut tmp = (a & 0x0F) + (m & 0x0F) + c;
+        if tmp > 0x09 {
+            tmp += 0x06;
+        }
+        tmp = if tmp <= 0x0F {
+            (tmp & 0x0F) + (a & 0xF0) + (m & 0xF0)
+        } else {
+            (tmp & 0x0F) + (a & 0xF0) + (m & 0xF0) + 0x10
+        };
+        self.s.set_zero((a + m + c) & 0xFF == 0);
+        self.s.set_negative(tmp & 0x80 == 0x80);
+        self.s.set_overflow((a ^ tmp) & 0x80 == 0x80 && (a ^ m) & 0x80 == 0);
+        if tmp & 0x1F0 > 0x90 {
+            tmp += 0x60;
+        }
+        self.s.set_carry(tmp & 0xFF0 > 0xF0);
+        self.a = tmp as u8;
+        if self.variant.is_cmos() {
+            self.set_flags_z_n(self.a);
+        }
+    }
+    pub(super) fn sbc_decimal(&mut self, m: u8) {
+        let a = self.a as u16;
+        let m = m as u16;
+        let borrow = 1 - self.s.get_carry() as u16;
+        let tmp = a.wrapping_sub(m).wrapping_sub(borrow);
+        let mut tmp_a = (a & 0x0F).wrapping_sub(m & 0x0F).wrapping_sub(borrow);
+        tmp_a = if tmp_a & 0x10 == 0x10 {
+            (tmp_a.wrapping_sub(6) & 0x0F) | (a & 0xF0).wrapping_sub(m & 0xF0).wrapping_sub(0x10)
+        } else {
+            (tmp_a & 0x0F) | (a & 0xF0).wrapping_sub(m & 0xF0)
+        };
+        if tmp_a & 0x100 == 0x100 {
+            tmp_a = tmp_a.wrapping_sub(0x60);
+        }
+        self.set_flags_z_n_c(tmp as u8, tmp < 0x100);
+        self.s.set_overflow((a ^ tmp) & 0x80 == 0x80 && (a ^ m) & 0x80 == 0x80);
+        self.a = tmp_a as u8;
+        if self.variant.is_cmos() {
+            self.a = Cpu::sbc_decimal_cmos(a, m, borrow);
+            self.set_flags_z_n(self.a);
+        }
+    }
+    // The CMOS adder subtracts $60 whenever the whole result borrows, not
+    // only when the high nibble does.
+    fn sbc_decimal_cmos(a: u16, m: u16, borrow: u16) -> u8 {
+        let (a, m, borrow) = (a as i16, m as i16, borrow as i16);
+        let lo = (a & 0x0F) - (m & 0x0F) - borrow;
+        let mut res = a - m - borrow;
+        if res < 0 {
+            res -= 0x60;
+        }
+        if lo < 0 {
+            res -= 0x06;
+        }
+        res as u8
+    }
+    // ARR runs its AND result through the decimal fixup logic without
+    // going through the adder.
+    pub(super) fn arr_decimal(&mut self, m: u8) {
+        let t = self.a & m;
+        let mut a = (t >> 1) | ((self.s.get_carry() as u8) << 7);
+        self.set_flags_z_n(a);
+        self.s.set_overflow((t ^ a) & 0x40 == 0x40);
+        if (t & 0x0F) + (t & 0x01) > 5 {
+            a = (a & 0xF0) | (a.wrapping_add(6) & 0x0F);
+        }
+        let high_fix = (t & 0xF0) as u16 + (t & 0x10) as u16 > 0x50;
+        if high_fix {
+            a = a.wrapping_add(0x60);
+        }
+        self.s.set_carry(high_fix);
+        self.a = a;
+    }
+}
@@ -0,0 +1,21 @@
+use core::fmt;
+
+/// Why `Cpu::run_instr` could not run an instruction. The CPU itself stays
+/// usable: a reset clears every one of these conditions.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CpuError {
+    /// The CPU is halted by JAM or STP at `pc` and only reset recovers it.
+    Halted { pc: u16, opcode: u8 },
+}
+
+impl fmt::Display for CpuError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CpuError::Halted { pc, opcode } => write!(f, "halted by opcode ${:02X} at ${:04X}", opcode, pc),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for CpuError {}
@@ -1,10 +1,9 @@
 #[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Flags(u8);
 impl Flags {
     pub fn new() -> Flags {
-        Flags{
-            0 : 0x05,
-        }
+        Flags(0x34)
     }
     pub fn set(&mut self,s:u8){
         self.0 = s;
@@ -46,6 +45,9 @@ impl Flags {
     pub fn get_overflow(&self) -> bool{
         self.gets(6)
     }
+    pub fn get_decimal(&self) -> bool{
+        self.gets(3)
+    }
     pub fn get_interrupt(&self) -> bool{
         self.gets(2)
     }
@@ -55,4 +57,4 @@ impl Flags {
     pub fn get_carry(&self) -> bool{
         self.gets(0)
     }
-}
\ No newline at end of file
+}
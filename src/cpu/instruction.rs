@@ -1,4 +1,5 @@
 #[derive(Copy, Clone, PartialEq, Debug, Ord, PartialOrd, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Instruction(pub u8);
 impl Instruction {
     pub fn get(&self) -> u8 {
@@ -0,0 +1,63 @@
+/// Hardware interrupt taken through the BRK sequence.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Interrupt {
+    Irq,
+    Nmi,
+    Reset,
+}
+
+impl Interrupt {
+    pub fn vector(self) -> u16 {
+        match self {
+            Interrupt::Irq => 0xFFFE,
+            Interrupt::Nmi => 0xFFFA,
+            Interrupt::Reset => 0xFFFC,
+        }
+    }
+}
+
+/// Devices pulling the wired-OR IRQ line low, one bit per source ID.
+/// The CPU sees IRQ asserted while any bit is set.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IrqSources(u32);
+
+impl IrqSources {
+    /// Number of distinct source IDs.
+    pub const COUNT: u8 = 32;
+
+    pub fn bits(self) -> u32 {
+        self.0
+    }
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+    /// Whether source `id` is currently asserting IRQ.
+    pub fn contains(self, id: u8) -> bool {
+        self.0 & Self::bit(id) != 0
+    }
+    /// The IDs of all asserting sources, lowest first.
+    pub fn iter(self) -> impl Iterator<Item = u8> {
+        (0..Self::COUNT).filter(move |&id| self.contains(id))
+    }
+    pub(super) fn from_bits(bits: u32) -> IrqSources {
+        IrqSources(bits)
+    }
+    // Whether `id` is in range; out-of-range IDs change nothing.
+    pub(super) fn set(&mut self, id: u8, asserted: bool) -> bool {
+        if id >= Self::COUNT {
+            return false;
+        }
+        if asserted {
+            self.0 |= Self::bit(id);
+        } else {
+            self.0 &= !Self::bit(id);
+        }
+        true
+    }
+    // IDs past `COUNT` have no bit.
+    fn bit(id: u8) -> u32 {
+        1u32.checked_shl(id as u32).unwrap_or(0)
+    }
+}
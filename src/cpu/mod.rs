@@ -1,38 +1,127 @@
 #![allow(non_snake_case)]
 
-mod flags;
-mod instruction;
+mod addressing;
+mod bus;
+mod cmos;
+mod decimal;
+mod error;
+pub(crate) mod flags;
+pub(crate) mod instruction;
+mod interrupt;
+mod opcode_info;
+mod quirks;
+mod run_state;
+mod save_state;
+mod variant;
+pub use self::bus::{Bus, DebugBus};
+pub use self::error::CpuError;
+pub use self::interrupt::IrqSources;
+pub use self::opcode_info::{opcode_info, AddrMode, OpcodeInfo, CMOS_OPCODES, NMOS_OPCODES};
+pub use self::quirks::CpuQuirks;
+pub use self::run_state::{RunState, StepOutcome};
+pub use self::save_state::{CpuState, StateError};
+pub use self::variant::Variant;
 use self::flags::Flags;
 use self::instruction::Instruction;
-use core::cmp::{Eq, PartialEq, Ord, PartialOrd};
-use core::ops::{Index, IndexMut};
-#[cfg(feature = "logging")]
-use log::*;
-use States::*;
+use self::interrupt::Interrupt;
+use core::cmp::{Eq, PartialEq};
+use Mode::*;
 
+/// Bus cycle sequence an opcode runs after its fetch.
 #[derive(PartialEq, Clone, Copy, Eq, Ord, PartialOrd, Debug)]
-enum States {
-    Fetch,
-    Decode,
-    Execute,
+enum Mode {
+    Implied,
+    Accumulator,
+    Immediate,
+    ZeroPage,
+    ZeroPageX,
+    ZeroPageY,
+    Absolute,
+    AbsoluteX,
+    AbsoluteY,
+    IndirectX,
+    IndirectY,
+    ZeroPageIndirect,
+    Relative,
+    JmpAbs,
+    JmpInd,
+    JmpIndX,
+    Jsr,
+    Rts,
+    Rti,
+    Brk,
+    Push,
+    Pull,
+    /// 65C02 one-byte NOPs, which finish with their opcode fetch.
+    Nop1,
+    /// 65C02 `$5C`, a three-byte NOP that takes eight cycles.
+    Nop8,
+    /// BBRn/BBSn: zero page test followed by a relative branch.
+    BitBranch,
+    /// WAI and STP.
+    Halt,
+}
+
+impl Mode {
+    // Highest step the sequence can be at between two cycles, over every
+    // operation and variant that uses it.
+    fn last_step(self) -> u8 {
+        match self {
+            Nop1 => 0,
+            Implied | Accumulator => 1,
+            Immediate | JmpAbs | Push | Halt => 2,
+            Relative | Pull => 3,
+            ZeroPage => 4,
+            ZeroPageX | ZeroPageY | Absolute | JmpInd | JmpIndX | Jsr | Rts | Rti => 5,
+            AbsoluteX | AbsoluteY | ZeroPageIndirect | Brk | BitBranch => 6,
+            IndirectX | IndirectY | Nop8 => 7,
+        }
+    }
+}
+
+/// What an opcode does with its operand once it has been addressed.
+#[derive(Clone, Copy, Debug)]
+enum Op {
+    Implied(fn(&mut Cpu)),
+    Read(fn(&mut Cpu, u8)),
+    Write(fn(&mut Cpu) -> u8),
+    Modify(fn(&mut Cpu, u8) -> u8),
 }
 
 #[derive(Clone, Debug)]
 pub struct Cpu {
-    //#[cfg(feature = "logging")]
-    //pub log_line: String,
     pub a: u8,
     pub x: u8,
     pub y: u8,
     pub s: Flags,
     pub sp: u8,
     pub pc: u16,
-    pub addr: Option<u16>,
-    pub cycles: isize,
-    pub in_nmi: bool,
+    pub addr: u16,
+    /// Cycles run since the CPU was created. Never reset.
+    pub cycles: u64,
     pub instruction: Instruction,
-    states: States,
-    current_instr: fn(&mut Cpu, &mut dyn IndexMut<u16, Output = u8>),
+    pub quirks: CpuQuirks,
+    pub variant: Variant,
+    pub state: RunState,
+    step: u8,
+    base: u16,
+    data: u8,
+    mode: Mode,
+    current_instr: Op,
+    irq_sources: IrqSources,
+    nmi_line: bool,
+    reset_line: bool,
+    // reset requested and its sequence not yet started
+    reset_pending: bool,
+    // NMI edge seen and not yet serviced
+    nmi_edge: bool,
+    // Interrupt wanted as sampled at the end of this cycle and of the
+    // previous one. The previous cycle's sample decides, which is how the
+    // chip ends up polling on the second-to-last cycle of an instruction.
+    poll: bool,
+    prev_poll: bool,
+    // being taken by the current BRK sequence; None for BRK itself
+    interrupt: Option<Interrupt>,
 }
 impl PartialEq for Cpu {
     fn eq(&self, other: &Cpu) -> bool {
@@ -47,533 +136,493 @@ impl PartialEq for Cpu {
 impl Eq for Cpu {}
 impl Cpu {
     pub fn new_test(pc: u16, sp: u8, a: u8, x: u8, y: u8, p: u8) -> Cpu {
-        let mut status = Flags::new();
-        status.set(p);
-        Cpu {
-            a,
-            x,
-            y,
-            s: status,
-            sp,
-            pc,
-            addr: None,
-            cycles: 0,
-            in_nmi: false,
-            instruction: Instruction(0xEA),
-            states: Fetch,
-            current_instr: Cpu::NOP,
-        }
+        let mut cpu = Cpu::new(Some(pc));
+        cpu.sp = sp;
+        cpu.a = a;
+        cpu.x = x;
+        cpu.y = y;
+        cpu.s.set(p);
+        cpu
     }
     pub fn new(init_pc: Option<u16>) -> Cpu {
-        let i_pc: u16;
-        if let Some(pc) = init_pc {
-            i_pc = pc;
-        } else {
-            i_pc = 0;
-        }
         Cpu {
-            //#[cfg(feature = "logging")]
-            //log_line: "".to_string(),
             a: 0,
             x: 0,
             y: 0,
             s: Flags::new(),
             sp: 0xFD,
-            pc: i_pc,
-            addr: None,
+            pc: init_pc.unwrap_or(0),
+            addr: 0,
             cycles: 0,
-            in_nmi: false,
             instruction: Instruction(0xEA),
-            states: Fetch,
-            current_instr: Cpu::NOP,
+            quirks: CpuQuirks::default(),
+            variant: Variant::Ricoh2A03,
+            state: RunState::Running,
+            step: 0,
+            base: 0,
+            data: 0,
+            mode: Implied,
+            current_instr: Op::Implied(Cpu::NOP),
+            irq_sources: IrqSources::default(),
+            nmi_line: false,
+            reset_line: false,
+            reset_pending: false,
+            nmi_edge: false,
+            poll: false,
+            prev_poll: false,
+            interrupt: None,
         }
     }
-    pub fn load16_instrs(&self, mem: &mut dyn IndexMut<u16, Output = u8>, addr: u16) -> u16{
-        let b0 = mem[addr];
+    pub fn load16_instrs<B: Bus + ?Sized>(&self, bus: &mut B, addr: u16) -> u16{
+        let b0 = bus.read(addr);
         let addrp1 =addr.wrapping_add(1);
-        let b1 = mem[addrp1];
+        let b1 = bus.read(addrp1);
         u16::from_le_bytes([b0, b1])
     }
-    pub fn pop_load16(&self, mem: &mut dyn IndexMut<u16, Output = u8>) -> u16{
-        let sp = if self.sp == 0xFF {
-             0x0
-        } else {
-            self.sp+1
-        };
-        let sp = sp as u16 + 0x100;
-        let b0 = mem[sp];
-        let b1 = mem[sp+1];
+    pub fn load16<B: Bus + ?Sized>(&self, bus: &mut B, addr: u16) -> u16 {
+        let addr2: u16 = if addr == 0xFF { 0x0 } else { addr.wrapping_add(1) };
+        let b0 = bus.read(addr);
+        let b1 = bus.read(addr2);
         u16::from_le_bytes([b0, b1])
     }
-    pub fn load16(&self, mem: &mut dyn IndexMut<u16, Output = u8>, addr: u16) -> u16 {
-        let addr2: u16;
-        if addr == 0xFF {
-            addr2 = 0x0
-        } else {
-            addr2 = addr + 1;
-        }
-        let b0 = mem[addr];
-        let b1 = mem[addr2];
-        u16::from_le_bytes([b0, b1])
-    }
-    pub fn store16(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>, addr: u16, val: u16) {
+    pub fn store16<B: Bus + ?Sized>(&mut self, bus: &mut B, addr: u16, val: u16) {
         let v = val.to_le_bytes();
-        mem[addr] = v[0];
-        mem[addr + 1] = v[1];
+        bus.write(addr, v[0]);
+        bus.write(addr.wrapping_add(1), v[1]);
     }
-    pub fn StackPush(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>, val:u8){
-        mem[self.sp as u16 + 0x100] = val;
-        if self.sp == 0x00{
-            self.sp = 0xFF;
-        } else {
-          self.sp -= 1;
-        }
-
+    pub fn StackPush<B: Bus + ?Sized>(&mut self, bus: &mut B, val:u8){
+        bus.write(self.sp as u16 + 0x100, val);
+        self.sp = self.sp.wrapping_sub(1);
     }
-    pub fn irq(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
-        if !self.s.get_interrupt() {
-            self.sp = self.sp.wrapping_sub(2);
-            let sp = self.sp as u16 + 0x100;
-            let pc = self.pc;
-            self.store16(mem, sp, pc);
-            self.pc = self.load16(mem, 0xFFFE);
-            self.sp = self.sp.wrapping_sub(1);
-            let sp = self.sp as u16 + 0x100;
-            let s = self.s.get();
-            mem[sp] = s;
+    fn StackPull<B: Bus + ?Sized>(&mut self, bus: &mut B) -> u8 {
+        self.sp = self.sp.wrapping_add(1);
+        bus.read(self.sp as u16 + 0x100)
+    }
+    /// Drives the level-triggered IRQ input for a single-device system.
+    /// This is IRQ source 0; with several devices, give each its own
+    /// source with `assert_irq` and `release_irq`.
+    pub fn set_irq_line(&mut self, asserted: bool) {
+        self.irq_sources.set(0, asserted);
+    }
+    /// Pulls IRQ source `id` low. Sources are wired-OR, so the line stays
+    /// asserted until every device has been acknowledged and released it.
+    /// Returns false and changes nothing if `id` is not below
+    /// `IrqSources::COUNT`.
+    pub fn assert_irq(&mut self, id: u8) -> bool {
+        self.irq_sources.set(id, true)
+    }
+    /// Releases IRQ source `id`. Returns false and changes nothing if `id`
+    /// is out of range, like `assert_irq`.
+    pub fn release_irq(&mut self, id: u8) -> bool {
+        self.irq_sources.set(id, false)
+    }
+    /// The sources currently asserting IRQ.
+    pub fn irq_sources(&self) -> IrqSources {
+        self.irq_sources
+    }
+    /// Drives the edge-triggered NMI input. Only the transition to
+    /// asserted is latched; holding the line does not retrigger.
+    pub fn set_nmi_line(&mut self, asserted: bool) {
+        if asserted && !self.nmi_line {
+            self.nmi_edge = true;
+        }
+        self.nmi_line = asserted;
+    }
+    /// Drives the RESET input. Asserting it abandons the current
+    /// instruction and holds the CPU idle; releasing it runs the reset
+    /// sequence, which also ends a WAI, STP or JAM halt.
+    pub fn set_reset_line(&mut self, asserted: bool) {
+        self.reset_line = asserted;
+        self.reset_pending |= asserted;
+    }
+    fn poll_interrupts(&mut self) {
+        self.prev_poll = self.poll;
+        self.poll = self.nmi_edge || (!self.irq_sources.is_empty() && !self.s.get_interrupt());
+    }
+    /// Powers the CPU up: clears the registers and runs the reset
+    /// sequence, leaving SP at $FD and P at $34 as on hardware.
+    pub fn power_on<B: Bus + ?Sized>(&mut self, bus: &mut B) {
+        self.a = 0;
+        self.x = 0;
+        self.y = 0;
+        self.sp = 0;
+        self.s = Flags::new();
+        self.reset(bus);
+    }
+    /// Pulses RESET and runs the 7-cycle reset sequence to completion.
+    /// A, X and Y are kept; SP drops by 3, I is set and PC is loaded from
+    /// $FFFC.
+    pub fn reset<B: Bus + ?Sized>(&mut self, bus: &mut B) {
+        self.set_reset_line(true);
+        self.set_reset_line(false);
+        self.tick(bus);
+        while self.step != 0 {
+            self.tick(bus);
         }
     }
-    pub fn nmi(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
-        self.in_nmi = true;
-        self.sp = self.sp.wrapping_sub(2);
-        let sp = self.sp as u16 + 0x100;
-        let pc = self.pc;
-        self.store16(mem, sp, pc);
-        self.pc = self.load16(mem, 0xFFFE);
-        self.sp = self.sp.wrapping_sub(1);
-        let sp = self.sp as u16 + 0x100;
-        let s = self.s.get();
-        mem[sp] = s;
+    /// Runs a single bus cycle: exactly one read or write. While the CPU
+    /// is waiting, stopped or jammed the clock runs but the bus stays idle.
+    pub fn tick<B: Bus + ?Sized>(&mut self, bus: &mut B) {
+        self.cycles += 1;
+        if self.reset_line {
+            self.state = RunState::Reset;
+            return;
+        }
+        if self.reset_pending {
+            self.reset_pending = false;
+            self.state = RunState::Running;
+            self.begin_interrupt(bus, Interrupt::Reset);
+            return;
+        }
+        match self.state {
+            RunState::Running => {}
+            // WAI resumes on any interrupt, even one masked by I. An
+            // unmasked one is taken straight away, before the next opcode.
+            RunState::Waiting => {
+                self.poll_interrupts();
+                if !self.irq_sources.is_empty() || self.nmi_edge {
+                    self.state = RunState::Running;
+                    self.prev_poll = self.poll;
+                }
+                return;
+            }
+            RunState::Stopped | RunState::Jammed { .. } | RunState::Reset => return,
+        }
+        if self.step == 0 {
+            self.fetch(bus);
+        } else {
+            self.execute(bus);
+        }
+        // the interrupt sequence itself does not poll
+        if self.mode != Brk {
+            self.poll_interrupts();
+        }
     }
-    pub fn start(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
-        self.cycles += 6;
-        let reset: u16 = self.load16(mem, 0xFFFC);
-        self.pc = reset;
+    fn execute<B: Bus + ?Sized>(&mut self, bus: &mut B) {
+        let step = self.step;
+        debug_assert!(step <= self.mode.last_step(), "{:?} at step {}", self.mode, step);
+        self.step += 1;
+        match self.mode {
+            Implied | Accumulator => self.implied(bus),
+            Immediate => self.immediate(bus, step),
+            ZeroPage => self.zero_page(bus, step),
+            ZeroPageX => self.zero_page_indexed(bus, step, self.x),
+            ZeroPageY => self.zero_page_indexed(bus, step, self.y),
+            Absolute => self.absolute(bus, step),
+            AbsoluteX => self.absolute_indexed(bus, step, self.x),
+            AbsoluteY => self.absolute_indexed(bus, step, self.y),
+            IndirectX => self.indexed_indirect_x(bus, step),
+            IndirectY => self.indirect_indexed_y(bus, step),
+            ZeroPageIndirect => self.zero_page_indirect(bus, step),
+            Relative => self.relative(bus, step),
+            JmpAbs => self.jmp_absolute(bus, step),
+            JmpInd => self.jmp_indirect(bus, step, 0),
+            JmpIndX => self.jmp_indirect(bus, step, self.x),
+            Jsr => self.jsr(bus, step),
+            Rts => self.rts(bus, step),
+            Rti => self.rti(bus, step),
+            Brk => self.brk(bus, step),
+            Push => self.push(bus, step),
+            Pull => self.pull(bus, step),
+            // finishes with its fetch and never gets here
+            Nop1 => self.step = 0,
+            Nop8 => self.nop8(bus, step),
+            BitBranch => self.bit_branch(bus, step),
+            Halt => self.halt(bus, step),
+        }
     }
-    pub fn run_instr(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
-        let pc = self.pc;
-        let val = mem[pc];
-        self.pc = self.pc.wrapping_add(1);
-        self.instruction.set(val);
-        self.current_instr = self.decode(mem);
-        (self.current_instr)(self, mem);
-        //self.cycles+=1;
-    }
-    pub fn run(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) -> isize {
-        self.cycles = 0;
-        match self.states {
-            Fetch => {
-                self.fetch(mem);
-                self.states = Decode;
-            }
-            Decode => {
-                self.current_instr = self.decode(mem);
-                self.states = Execute;
-            }
-            Execute => {
-                (self.current_instr)(self, mem);
-                self.states = Fetch;
+    /// Runs the current instruction to completion, or the next one when
+    /// called between instructions. A waiting CPU, or one held in reset,
+    /// only burns a single idle cycle, so the returned state tells the host
+    /// when to stop polling. A halted CPU does the same but reports why it
+    /// cannot continue.
+    pub fn run_instr<B: Bus + ?Sized>(&mut self, bus: &mut B) -> Result<StepOutcome, CpuError> {
+        let start = self.cycles;
+        loop {
+            self.tick(bus);
+            if self.step == 0 || self.state != RunState::Running {
+                break;
             }
         }
-        self.cycles
+        match self.state {
+            RunState::Jammed { addr, opcode } => Err(CpuError::Halted { pc: addr, opcode }),
+            RunState::Stopped => Err(CpuError::Halted {
+                pc: self.pc.wrapping_sub(1),
+                opcode: self.instruction.get(),
+            }),
+            state => Ok(StepOutcome {
+                cycles: self.cycles - start,
+                state,
+            }),
+        }
     }
-    fn fetch(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
+    /// Runs whole instructions until at least `budget` cycles have passed
+    /// and returns how far the last one overshot. Subtract the overshoot
+    /// from the next budget to keep a frame or audio loop in sync. A budget
+    /// of `u64::MAX` runs until the CPU halts.
+    pub fn run_cycles<B: Bus + ?Sized>(&mut self, bus: &mut B, budget: u64) -> Result<u64, CpuError> {
+        let end = self.cycles.saturating_add(budget);
+        while self.cycles < end {
+            self.run_instr(bus)?;
+        }
+        Ok(self.cycles - end)
+    }
+    /// Runs whole instructions until `done` returns true for the CPU
+    /// between two of them, and returns the cycles that took. `done` is
+    /// checked before the first instruction too.
+    pub fn run_until<B: Bus + ?Sized>(&mut self, bus: &mut B, mut done: impl FnMut(&Cpu) -> bool) -> Result<u64, CpuError> {
+        let start = self.cycles;
+        while !done(self) {
+            self.run_instr(bus)?;
+        }
+        Ok(self.cycles - start)
+    }
+    fn fetch<B: Bus + ?Sized>(&mut self, bus: &mut B) {
+        // An interrupt replaces the opcode with BRK and keeps PC where it
+        // is, so the fetch only happens on the bus.
+        if self.prev_poll {
+            let kind = if self.nmi_edge { Interrupt::Nmi } else { Interrupt::Irq };
+            self.begin_interrupt(bus, kind);
+            return;
+        }
         let pc = self.pc;
-        let val = mem[pc];
-        self.pc += 1;
-        self.cycles += 1;
+        let val = bus.read(pc);
+        self.pc = self.pc.wrapping_add(1);
         self.instruction.set(val);
-        //self.log_line = format!("{:04X}  {:02X}                                        A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X} CYC:{:}\n",pc,val,self.a,self.x,self.y,self.s.get(),self.sp, self.cycles);
-        //trace!("{:04X}  {:02X}        A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X} CYC:{:08X}",pc,val,self.a,self.x,self.y,self.s.get(),self.sp, self.cycles);
+        let (mode, instr) = self.decode();
+        self.mode = mode;
+        self.current_instr = instr;
+        self.step = if mode == Nop1 { 0 } else { 1 };
+    }
+    fn begin_interrupt<B: Bus + ?Sized>(&mut self, bus: &mut B, kind: Interrupt) {
+        bus.read(self.pc);
+        self.instruction.set(0x00);
+        self.interrupt = Some(kind);
+        self.poll = false;
+        self.prev_poll = false;
+        self.mode = Brk;
+        self.current_instr = Op::Implied(Cpu::NOP);
+        self.step = 1;
+    }
+    fn decode(&self) -> (Mode, Op) {
+        if self.variant.is_cmos() {
+            return self.decode_cmos();
+        }
+        self.decode_nmos()
     }
-    fn decode(
-        &mut self,
-        mem: &mut dyn IndexMut<u16, Output = u8>,
-    ) -> fn(&mut Cpu, &mut dyn IndexMut<u16, Output = u8>) {
-        self.cycles += 1;
+    fn decode_nmos(&self) -> (Mode, Op) {
         match self.instruction.get() {
-            0x00 => Cpu::BRK,
+            0x00 => (Brk, Op::Implied(Cpu::NOP)),
             0x02 | 0x12 | 0x22 | 0x32 | 0x42 | 0x52 | 0x62 | 0x72 | 0x92 | 0xB2 | 0xD2 | 0xF2 => {
-                Cpu::JAM
-            }
-            0x03 => {
-                self.addr = Some(self.indexed_indirect_x(mem));
-                Cpu::SLO
-            }
-            0x07 => {
-                self.addr = Some(self.zero_page(mem));
-                Cpu::SLO
-            }
-            0x0F => {
-                self.addr = Some(self.absolute(mem));
-                Cpu::SLO
-            }
-            0x08 => Cpu::PHP,
-            0x0B | 0x2B => {
-                self.addr = Some(self.immediate());
-                Cpu::ANC
-            }
-            0x13 =>{
-                self.addr = Some(self.indirect_indexed_y(mem));
-                Cpu::SLO
-            }
-            0x17 =>{
-                self.addr = Some(self.zero_page_r(mem));
-                Cpu::SLO
-            }
-            0x18 => Cpu::CLC,
-            0x1B =>{
-                self.addr = Some(self.absolute_y(mem));
-                Cpu::SLO
-            }
-            0x1F =>{
-                self.addr = Some(self.absolute_x(mem));
-                Cpu::SLO
-            }
-            0x20 => {
-                self.addr = Some(self.absolute(mem));
-                Cpu::JSR
-            }
-            0x23 => {
-                self.addr = Some(self.indexed_indirect_x(mem));
-                Cpu::RLA
-            }
-            0x26 => {
-                self.addr = Some(self.zero_page(mem));
-                Cpu::ROL
-            }
-            0x27 => {
-                self.addr = Some(self.zero_page(mem));
-                Cpu::RLA
-            }
-            0x28 => Cpu::PLP,
-            0x2F => {
-                self.addr = Some(self.absolute(mem));
-                Cpu::RLA
-            }
-            0x33 => {
-                self.addr = Some(self.indirect_indexed_y(mem));
-                Cpu::RLA
-            }
-            0x37 => {
-                self.addr = Some(self.zero_page_r(mem));
-                Cpu::RLA
-            }
-            0x38 => Cpu::SEC,
-            0x3B => {
-                self.addr = Some(self.absolute_y(mem));
-                Cpu::RLA
-            }
-            0x3F => {
-                self.addr = Some(self.absolute_x(mem));
-                Cpu::RLA
-            }
-            0x40 => Cpu::RTI,
-            0x43 => {
-                self.addr = Some(self.indexed_indirect_x(mem));
-                Cpu::SRE
-            }
-            0x47 =>{
-                self.addr = Some(self.zero_page(mem));
-                Cpu::SRE
-            }
-            0x48 => Cpu::PHA,
-            0x4B => {
-                self.addr = Some(self.immediate());
-                Cpu::ALR
-            },
-            0x4F =>{
-                self.addr = Some(self.absolute(mem));
-                Cpu::SRE
-            }
-            0x53 => {
-                self.addr = Some(self.indirect_indexed_y(mem));
-                Cpu::SRE
-            }
-            0x57 => {
-                self.addr = Some(self.zero_page_r(mem));
-                Cpu::SRE
-            }
-            0x58 => Cpu::CLI,
-            0x5B => {
-                self.addr = Some(self.absolute_y(mem));
-                Cpu::SRE
-            }
-            0x5D => {
-                self.addr = Some(self.absolute_x(mem));
-                Cpu::EOR
-            }
-            0x5F => {
-                self.addr = Some(self.absolute_x(mem));
-                Cpu::SRE
-            }
-            0x60 => Cpu::RTS,
-            0x61 => {
-                self.addr = Some(self.indexed_indirect_x(mem));
-                Cpu::ADC
-            }
-            0x63 => {
-                self.addr = Some(self.indexed_indirect_x(mem));
-                Cpu::RRA
-            }
-            0x68 => Cpu::PLA,
-            0x78 => Cpu::SEI,
-            0x88 => Cpu::DEY,
-            0x8A => Cpu::TXA,
-            0x98 => Cpu::TYA,
-            0x9A => Cpu::TXS,
-            0xA8 => Cpu::TAY,
-            0xAA => Cpu::TAX,
-            0xB8 => Cpu::CLV,
-            0xBA => Cpu::TSX,
-            0xC8 => Cpu::INY,
-            0xCA => Cpu::DEX,
-            0xD8 => Cpu::CLD,
-            0xE8 => Cpu::INX,
-            0xEA | 0x1A | 0x3A | 0x5A => Cpu::NOP,
-            0x04 | 0x14 | 0x34 | 0x44 | 0x54 | 0x64 => {
-                self.pc += 1;
-                Cpu::NOP
-            }
-            0x0C | 0x1C | 0x3C | 0x5C => {
-                self.pc += 2;
-                Cpu::NOP
-            }
-
-            0xF8 => Cpu::SED,
-            _ => match self.instruction.cc() {
-                0 => {
-                    if let Some(i) = self.addressing0(mem) {
-                        self.addr = Some(i);
-                        match self.instruction.aaa() {
-                            1 => Cpu::BIT,
-                            2 => Cpu::JMP,
-                            3 => Cpu::JMI,
-                            4 => Cpu::STY,
-                            5 => Cpu::LDY,
-                            6 => Cpu::CPY,
-                            7 => Cpu::CPX,
-                            _ => panic!(),
-                        }
-                    } else {
-                        Cpu::relative
-                    }
-                }
-                1 => {
-                    self.addressing1(mem);
-                    match self.instruction.aaa() {
-                        0 => Cpu::ORA,
-                        1 => Cpu::AND,
-                        2 => Cpu::EOR,
-                        3 => Cpu::ADC,
-                        4 => Cpu::STA,
-                        5 => Cpu::LDA,
-                        6 => Cpu::CMP,
-                        7 => Cpu::SBC,
-                        _ => panic!(),
-                    }
-                }
-                2 => {
-                    self.addressing2(mem);
-                    match self.instruction.aaa() {
-                        0 => Cpu::ASL,
-                        1 => Cpu::ROL,
-                        2 => Cpu::LSR,
-                        3 => Cpu::ROR,
-                        4 => Cpu::STX,
-                        5 => Cpu::LDX,
-                        6 => Cpu::DEC,
-                        7 => Cpu::INC,
-                        _ => panic!(),
-                    }
-                }
-                _ => panic!("instr:{:02x}", self.instruction.0),
-            },
+                (Implied, Op::Implied(Cpu::JAM))
+            }
+            0x08 => (Push, Op::Write(Cpu::PHP)),
+            0x0B | 0x2B => (Immediate, Op::Read(Cpu::ANC)),
+            0x18 => (Implied, Op::Implied(Cpu::CLC)),
+            0x20 => (Jsr, Op::Implied(Cpu::NOP)),
+            0x26 => (ZeroPage, Op::Modify(Cpu::ROL)),
+            0x28 => (Pull, Op::Read(Cpu::PLP)),
+            0x38 => (Implied, Op::Implied(Cpu::SEC)),
+            0x40 => (Rti, Op::Implied(Cpu::NOP)),
+            0x48 => (Push, Op::Write(Cpu::PHA)),
+            0x4B => (Immediate, Op::Read(Cpu::ALR)),
+            0x6B => (Immediate, Op::Read(Cpu::ARR)),
+            0x8B => (Immediate, Op::Read(Cpu::XAA)),
+            0x93 => (IndirectY, Op::Write(Cpu::SHA)),
+            0x9B => (AbsoluteY, Op::Write(Cpu::TAS)),
+            0x9C => (AbsoluteX, Op::Write(Cpu::SHY)),
+            0x9E => (AbsoluteY, Op::Write(Cpu::SHX)),
+            0x9F => (AbsoluteY, Op::Write(Cpu::SHA)),
+            0xAB => (Immediate, Op::Read(Cpu::LXA)),
+            0xBB => (AbsoluteY, Op::Read(Cpu::LAS)),
+            0xCB => (Immediate, Op::Read(Cpu::SBX)),
+            0xEB => (Immediate, Op::Read(Cpu::SBC)),
+            0x4C => (JmpAbs, Op::Implied(Cpu::NOP)),
+            0x58 => (Implied, Op::Implied(Cpu::CLI)),
+            0x5D => (AbsoluteX, Op::Read(Cpu::EOR)),
+            0x60 => (Rts, Op::Implied(Cpu::NOP)),
+            0x61 => (IndirectX, Op::Read(Cpu::ADC)),
+            0x68 => (Pull, Op::Read(Cpu::PLA)),
+            0x6C => (JmpInd, Op::Implied(Cpu::NOP)),
+            0x78 => (Implied, Op::Implied(Cpu::SEI)),
+            0x88 => (Implied, Op::Implied(Cpu::DEY)),
+            0x8A => (Implied, Op::Implied(Cpu::TXA)),
+            0x98 => (Implied, Op::Implied(Cpu::TYA)),
+            0x9A => (Implied, Op::Implied(Cpu::TXS)),
+            0xA8 => (Implied, Op::Implied(Cpu::TAY)),
+            0xAA => (Implied, Op::Implied(Cpu::TAX)),
+            0xB8 => (Implied, Op::Implied(Cpu::CLV)),
+            0xBA => (Implied, Op::Implied(Cpu::TSX)),
+            0xC8 => (Implied, Op::Implied(Cpu::INY)),
+            0xCA => (Implied, Op::Implied(Cpu::DEX)),
+            0xD8 => (Implied, Op::Implied(Cpu::CLD)),
+            0xE8 => (Implied, Op::Implied(Cpu::INX)),
+            0xEA | 0x1A | 0x3A | 0x5A | 0x7A | 0xDA | 0xFA => (Implied, Op::Implied(Cpu::NOP)),
+            0x80 | 0x82 | 0x89 | 0xC2 | 0xE2 => (Immediate, Op::Read(Cpu::IGN)),
+            0x04 | 0x44 | 0x64 => (ZeroPage, Op::Read(Cpu::IGN)),
+            0x14 | 0x34 | 0x54 | 0x74 | 0xD4 | 0xF4 => (ZeroPageX, Op::Read(Cpu::IGN)),
+            0x0C => (Absolute, Op::Read(Cpu::IGN)),
+            0x1C | 0x3C | 0x5C | 0x7C | 0xDC | 0xFC => (AbsoluteX, Op::Read(Cpu::IGN)),
+            0xF8 => (Implied, Op::Implied(Cpu::SED)),
+            _ => self.decode_columns(),
         }
     }
-    fn addressing0(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) -> Option<u16> {
-        match self.instruction.bbb() {
-            0 => Some(self.immediate()),
-            1 => Some(self.zero_page(mem)),
-            3 => Some(self.absolute(mem)),
-            4 => None,
-            5 => Some(self.zero_page_r(mem)),
-            7 => Some(self.absolute_x(mem)),
-            _ => None,
+    // The regular part of the opcode matrix: aaa picks the operation, bbb
+    // the addressing and cc the column.
+    fn decode_columns(&self) -> (Mode, Op) {
+        match self.instruction.cc() {
+            0 => {
+                let mode = self.addressing0();
+                if mode == Relative {
+                    return (Relative, Op::Implied(Cpu::NOP));
+                }
+                let op = match self.instruction.aaa() {
+                    1 => Op::Read(Cpu::BIT),
+                    4 => Op::Write(Cpu::STY),
+                    5 => Op::Read(Cpu::LDY),
+                    6 => Op::Read(Cpu::CPY),
+                    7 => Op::Read(Cpu::CPX),
+                    // BRK, RTI, RTS, the jumps and NOPs, all matched by
+                    // decode_nmos
+                    _ => Op::Read(Cpu::IGN),
+                };
+                (mode, op)
+            }
+            1 => (self.addressing1(), self.alu_op()),
+            2 => {
+                let op = match self.instruction.aaa() {
+                    0 => Op::Modify(Cpu::ASL),
+                    1 => Op::Modify(Cpu::ROL),
+                    2 => Op::Modify(Cpu::LSR),
+                    3 => Op::Modify(Cpu::ROR),
+                    4 => Op::Write(Cpu::STX),
+                    5 => Op::Read(Cpu::LDX),
+                    6 => Op::Modify(Cpu::DEC),
+                    _ => Op::Modify(Cpu::INC),
+                };
+                (self.addressing2(), op)
+            }
+            _ => {
+                let op = match self.instruction.aaa() {
+                    0 => Op::Modify(Cpu::SLO),
+                    1 => Op::Modify(Cpu::RLA),
+                    2 => Op::Modify(Cpu::SRE),
+                    3 => Op::Modify(Cpu::RRA),
+                    4 => Op::Write(Cpu::SAX),
+                    5 => Op::Read(Cpu::LAX),
+                    6 => Op::Modify(Cpu::DCP),
+                    _ => Op::Modify(Cpu::ISC),
+                };
+                (self.addressing3(), op)
+            }
         }
     }
-    fn addressing1(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
-        self.addr = Some(match self.instruction.bbb() {
-            0 => self.indexed_indirect_x(mem),
-            1 => self.zero_page(mem),
-            2 => self.immediate(),
-            3 => self.absolute(mem),
-            4 => self.indirect_indexed_y(mem),
-            5 => self.zero_page_r(mem),
-            6 => self.absolute_y(mem),
-            7 => self.absolute_x(mem),
-            _ => 0,
-        })
-    }
-    fn addressing2(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
-        self.addr = match self.instruction.bbb() {
-            0 => Some(self.immediate()),
-            1 => Some(self.zero_page(mem)),
-            2 => None,
-            3 => Some(self.absolute(mem)),
-            5 => Some(self.zero_page_r2(mem)),
-            7 => Some(match self.instruction.aaa() {
-                5 => self.absolute_y(mem),
-                _ => self.absolute_x(mem),
-            }),
-            _ => None,
+    fn alu_op(&self) -> Op {
+        match self.instruction.aaa() {
+            0 => Op::Read(Cpu::ORA),
+            1 => Op::Read(Cpu::AND),
+            2 => Op::Read(Cpu::EOR),
+            3 => Op::Read(Cpu::ADC),
+            4 => Op::Write(Cpu::STA),
+            5 => Op::Read(Cpu::LDA),
+            6 => Op::Read(Cpu::CMP),
+            _ => Op::Read(Cpu::SBC),
         }
     }
-    fn indirect_indexed_y(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) -> u16 {
-        let pc = self.pc;
-        let id = mem[pc];
-        let idix = self.load16(mem, id as u16);
-        let idixy = idix.wrapping_add(self.y as u16);
-        self.cycles += 4;
-        self.pc += 1;
-        idixy
-    }
-    fn indexed_indirect_x(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) -> u16 {
-        let pc = self.pc;
-        let x = self.x;
-        let id = mem[pc].wrapping_add(x);
-        self.pc = self.pc.wrapping_add(1);
-        let ixid = self.load16(mem, id as u16);
-        self.cycles += 4;
-        ixid
-    }
-    fn immediate(&mut self) -> u16 {
-        self.pc = self.pc.wrapping_add(1);
-        self.pc.wrapping_sub(1)
-    }
-    fn zero_page(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) -> u16 {
-        let pc = self.pc;
-        self.cycles += 1;
-        let zp = mem[pc];
-        self.pc = self.pc.wrapping_add(1);
-        zp as u16
-    }
-    fn zero_page_r(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) -> u16 {
-        let pc = self.pc;
-        let zpr = mem[pc];
-        self.pc += 1;
-        self.cycles += 2;
-        zpr.wrapping_add(self.x) as u16
+    fn addressing0(&self) -> Mode {
+        match self.instruction.bbb() {
+            0 => Immediate,
+            1 => ZeroPage,
+            3 => Absolute,
+            5 => ZeroPageX,
+            7 => AbsoluteX,
+            _ => Relative,
+        }
     }
-    fn zero_page_r2(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) -> u16 {
-        let pc = self.pc;
-        let zpr = mem[pc];
-        self.pc += 1;
-        self.cycles += 2;
-        match self.instruction.aaa() {
-            4 | 5 => zpr.wrapping_add(self.y) as u16,
-            _ => zpr.wrapping_add(self.x) as u16,
+    fn addressing1(&self) -> Mode {
+        match self.instruction.bbb() {
+            0 => IndirectX,
+            1 => ZeroPage,
+            2 => Immediate,
+            3 => Absolute,
+            4 => IndirectY,
+            5 => ZeroPageX,
+            6 => AbsoluteY,
+            _ => AbsoluteX,
         }
     }
-    fn zero_page_y(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) -> u16 {
-        let pc = self.pc;
-        let zpr = mem[pc];
-        self.pc += 1;
-        self.cycles += 2;
-        zpr.wrapping_add(self.y) as u16
-    }
-    fn absolute(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) -> u16 {
-        let id = self.load16_instrs(mem, self.pc);
-        self.pc = self.pc.wrapping_add(2);
-        self.cycles += 2;
-        id
-    }
-    fn absolute_x(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) -> u16 {
-        let pc = self.pc;
-        let mut idx = self.load16_instrs(mem, pc);
-        idx =  idx.wrapping_add(self.x as u16);
-        self.pc = self.pc.wrapping_add(2);
-        self.cycles += 3;
-        idx
+    fn addressing2(&self) -> Mode {
+        match self.instruction.bbb() {
+            0 => Immediate,
+            1 => ZeroPage,
+            3 => Absolute,
+            5 => match self.instruction.aaa() {
+                4 | 5 => ZeroPageY,
+                _ => ZeroPageX,
+            },
+            7 => match self.instruction.aaa() {
+                5 => AbsoluteY,
+                _ => AbsoluteX,
+            },
+            _ => Accumulator,
+        }
     }
-    fn absolute_y(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) -> u16 {
-        let pc = self.pc;
-        let mut idy = self.load16(mem, pc);
-        idy = idy.wrapping_add(self.y as u16);
-        self.pc += 2;
-        self.cycles += 3;
-        idy
+    // The illegal column borrows the addressing of the other two, including
+    // the X/Y swap for the SAX/LAX rows.
+    fn addressing3(&self) -> Mode {
+        match (self.instruction.bbb(), self.instruction.aaa()) {
+            (0, _) => IndirectX,
+            (1, _) => ZeroPage,
+            (2, _) => Immediate,
+            (3, _) => Absolute,
+            (4, _) => IndirectY,
+            (5, 4 | 5) => ZeroPageY,
+            (5, _) => ZeroPageX,
+            (6, _) => AbsoluteY,
+            (_, 4 | 5) => AbsoluteY,
+            _ => AbsoluteX,
+        }
     }
-    fn relative(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
+    fn branch_taken(&self) -> bool {
+        // BRA, which only decodes as a branch on the 65C02
+        if self.instruction.get() == 0x80 {
+            return true;
+        }
         match self.instruction.xx() {
-            0 => {
-                if self.s.get_negative() == self.instruction.y() {
-                    self.branch(mem)
-                }
-            }
-            1 => {
-                if self.s.get_overflow() == self.instruction.y() {
-                    self.branch(mem)
-                }
-            }
-            2 => {
-                if self.s.get_carry() == self.instruction.y() {
-                    self.branch(mem)
-                }
-            }
-            3 => {
-                if self.s.get_zero() == self.instruction.y() {
-                    self.branch(mem)
-                }
-            }
-            _ => {}
-        };
-        self.pc += 1;
-    }
-    fn branch(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
-        let pc = self.pc;
-        let offset = mem[pc] as i8;
-        self.pc = (self.pc as i32).wrapping_add(offset as i32) as u16;
-        if (pc / 256) != (self.pc / 256) {
-            self.cycles += 2;
+            0 => self.s.get_negative() == self.instruction.y(),
+            1 => self.s.get_overflow() == self.instruction.y(),
+            2 => self.s.get_carry() == self.instruction.y(),
+            _ => self.s.get_zero() == self.instruction.y(),
         }
-        self.cycles += 1;
     }
-    fn JAM(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {}
-    fn ORA(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
-        let addr = self.addr;
-        let m = mem[addr.unwrap()];
+    fn JAM(&mut self) {
+        #[cfg(feature = "logging")]
+        log::warn!("JAM ${:02X} at ${:04X}", self.instruction.get(), self.pc.wrapping_sub(1));
+        self.state = RunState::Jammed {
+            addr: self.pc.wrapping_sub(1),
+            opcode: self.instruction.get(),
+        };
+    }
+    fn ORA(&mut self, m: u8) {
         let a = self.a | m;
         self.set_flags_z_n(a);
         self.a = a;
     }
-    fn AND(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
-        let addr = self.addr;
-        let m = mem[addr.unwrap()];
+    fn AND(&mut self, m: u8) {
         let a = self.a & m;
         self.set_flags_z_n(a);
         self.a = a;
     }
-    fn EOR(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
-        let addr = self.addr.unwrap();
-        let m = mem[addr];
+    fn EOR(&mut self, m: u8) {
         let a = self.a ^ m;
         self.a = a;
         self.set_flags_z_n(a);
     }
-    fn ADC(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
-        let addr = self.addr.unwrap();
-        let m = mem[addr] as u16;
+    fn ADC(&mut self, m: u8) {
+        if self.decimal_mode() {
+            return self.adc_decimal(m);
+        }
+        let m = m as u16;
         let tmp = m + self.a as u16  + self.s.get_carry() as u16;
 
         self.s.set_negative(tmp & 0x80 == 0x80);
@@ -581,356 +630,275 @@ impl Cpu {
         self.s.set_carry(tmp > 0xFF);
         self.a = tmp as u8;
         self.s.set_zero(self.a == 0);
-        /*
-        let c = self.s.get_carry() as i8;
-        let (mc, o) = m.overflowing_add(c);
-        let (_, c) = (self.a as i8).overflowing_add(mc);
-        let o = c || o;
-        let (a, c) = self.a.overflowing_add(mc as u8);
-        self.a = a as u8;
-        self.set_flags_z_n_c_o(a, o, c);
-        */
-    }
-    fn STA(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
-        let addr = self.addr;
-        let a = self.a;
-        mem[addr.unwrap()] = a;
-    }
-    fn LDA(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
-        let addr = self.addr;
-        let val = mem[addr.unwrap()];
-        self.set_flags_z_n(val);
-        self.a = val;
-    }
-    fn CMP(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
-        let addr = self.addr;
-        let val = mem[addr.unwrap()];
-        let (res, o) = self.a.overflowing_sub(val);
-        self.set_flags_z_n_c(res, !o);
-    }
-    fn SBC(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
-        let addr = self.addr;
-        let m = mem[addr.unwrap()];
-        let cf = self.s.get_carry() as u8;
-        let (mc, o) = (self.a as i8).overflowing_sub(m as i8);
-        let (_, of) = mc.overflowing_sub(1 - cf as i8);
-        let (a, c) = self.a.overflowing_sub(m);
-        let (a, cr) = a.overflowing_sub(1 - cf);
-        let o = o || of;
-        let c = !(c || cr);
-        self.a = a as u8;
-        self.set_flags_z_n_c_o(a, o, c);
     }
-    //{ "name": "0e 86 ef", "initial": { "pc": 254, "s": 226, "a": 7, "x": 99, "y": 166, "p": 239, "ram": [ [254, 14], [255, 134], [256, 239], [61318, 149], [257, 103]]}, "final": { "pc": 257, "s": 226, "a": 7, "x": 99, "y": 166, "p": 109, "ram": [ [254, 14], [255, 134], [256, 239], [257, 103], [61318, 42]]}, "cycles": [ [254, 14, "read"], [255, 134, "read"], [256, 239, "read"], [61318, 149, "read"], [61318, 149, "write"], [61318, 42, "write"]] },
-
-    fn ASL(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
-        if let Some(addr) = self.addr {
-            //println!("addr: {:x}", addr);
-            let mut m = mem[addr];
-            let m2 = m;
-            m = m << 1;
-            mem[addr] = m;
-            self.set_flags_z_n_c(m, m2 & 0x80 == 0x80);
-        } else {
-            let a1 = self.a << 1;
-            let a2 = self.a;
-            self.a = a1;
-            self.set_flags_z_n_c(a1, a2 & 0x80 == 0x80);
-        }
-    }
-    fn ROL(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
-        if let Some(addr) = self.addr {
-            let m = mem[addr];
-            let m2 = m;
-            let (m, _) = m.overflowing_mul(2);
-            let (m, _) = m.overflowing_add(self.s.get_carry() as u8);
-            mem[addr] = m;
-            self.set_flags_z_n_c(m, m2 & 0x80 == 0x80);
-        } else {
-            let (a1, _) = self.a.overflowing_mul(2);
-            let (a1, _) = a1.overflowing_add(self.s.get_carry() as u8);
-            let a2 = self.a;
-            self.a = a1;
-            self.set_flags_z_n_c(a1, a2 & 0x80 == 0x80);
-        }
+    fn STA(&mut self) -> u8 {
+        self.a
     }
-    fn LSR(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
-        if let Some(addr) = self.addr {
-            let mut m = mem[addr];
-            let m2 = m;
-            m = m >> 1;
-            mem[addr] = m;
-            self.set_flags_z_n_c(m, m2 & 1 == 1);
-        } else {
-            let a1 = self.a >> 1;
-            let a2 = self.a;
-            self.a = a1;
-            self.set_flags_z_n_c(a1, a2 & 1 == 1);
-        }
+    fn LDA(&mut self, m: u8) {
+        self.set_flags_z_n(m);
+        self.a = m;
     }
-    fn ROR(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
-        if let Some(addr) = self.addr {
-            let mut m = mem[addr];
-            let m2 = m;
-            m = m / 2 + ((self.s.get_carry() as u8) << 7);
-            mem[addr] = m;
-            self.set_flags_z_n_c(m, m2 & 1 == 1);
-        } else {
-            let a1 = self.a / 2 + ((self.s.get_carry() as u8) << 7);
-            let a2 = self.a;
-            self.a = a1 as u8;
-            self.set_flags_z_n_c(a1 as u8, a2 & 1 == 1);
-        }
+    fn CMP(&mut self, m: u8) {
+        let (res, o) = self.a.overflowing_sub(m);
+        self.set_flags_z_n_c(res, !o);
     }
-    fn STX(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
-        if let Some(addr) = self.addr {
-            let x = self.x;
-            mem[addr] = x;
+    fn SBC(&mut self, m: u8) {
+        if self.decimal_mode() {
+            return self.sbc_decimal(m);
         }
+        self.ADC(!m);
     }
-    fn LDX(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
-        if let Some(addr) = self.addr {
-            let val = mem[addr];
-            self.set_flags_z_n(val);
-            self.x = val;
-        }
+    //{ "name": "0e 86 ef", "initial": { "pc": 254, "s": 226, "a": 7, "x": 99, "y": 166, "p": 239, "ram": [ [254, 14], [255, 134], [256, 239], [61318, 149], [257, 103]]}, "final": { "pc": 257, "s": 226, "a": 7, "x": 99, "y": 166, "p": 109, "ram": [ [254, 14], [255, 134], [256, 239], [257, 103], [61318, 42]]}, "cycles": [ [254, 14, "read"], [255, 134, "read"], [256, 239, "read"], [61318, 149, "read"], [61318, 149, "write"], [61318, 42, "write"]] },
+
+    fn ASL(&mut self, m: u8) -> u8 {
+        let res = m << 1;
+        self.set_flags_z_n_c(res, m & 0x80 == 0x80);
+        res
+    }
+    fn ROL(&mut self, m: u8) -> u8 {
+        let res = (m << 1) | self.s.get_carry() as u8;
+        self.set_flags_z_n_c(res, m & 0x80 == 0x80);
+        res
+    }
+    fn LSR(&mut self, m: u8) -> u8 {
+        let res = m >> 1;
+        self.set_flags_z_n_c(res, m & 1 == 1);
+        res
+    }
+    fn ROR(&mut self, m: u8) -> u8 {
+        let res = (m >> 1) | ((self.s.get_carry() as u8) << 7);
+        self.set_flags_z_n_c(res, m & 1 == 1);
+        res
+    }
+    fn STX(&mut self) -> u8 {
+        self.x
+    }
+    fn LDX(&mut self, m: u8) {
+        self.set_flags_z_n(m);
+        self.x = m;
     }
-    fn DEC(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
-        if let Some(addr) = self.addr {
-            let m = mem[addr].wrapping_sub(1);
-            mem[addr] = m;
-            self.set_flags_z_n(m);
-        }
+    fn DEC(&mut self, m: u8) -> u8 {
+        let res = m.wrapping_sub(1);
+        self.set_flags_z_n(res);
+        res
     }
-    fn INC(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
-        if let Some(addr) = self.addr {
-            let m = mem[addr].wrapping_add(1);
-            mem[addr] = m;
-            self.set_flags_z_n(m);
-        }
+    fn INC(&mut self, m: u8) -> u8 {
+        let res = m.wrapping_add(1);
+        self.set_flags_z_n(res);
+        res
     }
-    fn BIT(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
-        let addr = self.addr;
-        let m = mem[addr.unwrap()];
+    fn BIT(&mut self, m: u8) {
         let res = self.a & m;
         self.set_flags_z_n_o(res, m);
     }
-    fn JMP(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
-        self.pc = self.addr.unwrap();
-        self.cycles += 2;
-    }
-    fn JMI(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
-        let addr = self.addr;
-        let valL = mem[addr.unwrap()];
-        let addrH = self.addr.unwrap() & 0xFF00;
-        let addrL = self.addr.unwrap() as u8;
-        let addr2 = addrH | addrL.wrapping_add(1) as u16;
-        let valH = mem[addr2];
-        self.pc = valL as u16 | (valH as u16) << 8;
-    }
-    fn STY(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
-        let y = self.y;
-        let addr = self.addr;
-        mem[addr.unwrap()] = y;
+    fn STY(&mut self) -> u8 {
+        self.y
     }
-    fn LDY(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
-        let addr = self.addr;
-        let m = mem[addr.unwrap()];
+    fn LDY(&mut self, m: u8) {
         self.y = m;
         self.set_flags_z_n(m);
     }
-    fn CPY(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
-        let addr = self.addr;
-        let val = mem[addr.unwrap()];
-        let y = self.y;
-        let (res, o) = y.overflowing_sub(val);
+    fn CPY(&mut self, m: u8) {
+        let (res, o) = self.y.overflowing_sub(m);
         self.set_flags_z_n_c(res, !o);
     }
-    fn CPX(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
-        let addr = self.addr;
-        let val = mem[addr.unwrap()];
-        let x = self.x;
-        let (res, o) = x.overflowing_sub(val);
+    fn CPX(&mut self, m: u8) {
+        let (res, o) = self.x.overflowing_sub(m);
         self.set_flags_z_n_c(res, !o);
     }
-    fn BRK(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
-        self.cycles += 5;
-        let pc = self.pc.wrapping_add(1);
-        self.StackPush(mem, (pc >> 8) as u8);
-        self.StackPush(mem, pc as u8);
-        self.StackPush(mem, self.s.get() | 0x10);
-        self.s.set_interrupt(true);
-        self.pc = self.load16(mem, 0xFFFE);
-    }
-    fn JSR(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
-        let pc = self.pc.wrapping_sub(1);
-        self.StackPush(mem,(pc >> 8) as u8);
-        self.StackPush(mem, pc as u8);
-        self.pc = self.addr.unwrap();
-        self.cycles += 2;
+    fn PHP(&mut self) -> u8 {
+        self.s.get() | 0x10
     }
-    fn RTI(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
-        self.in_nmi = false;
-        self.sp = self.sp.wrapping_add(1);
-        let s = mem[self.sp as u16 + 0x100];
-        self.s.set((s | 0x20) & 0xEF);
-        self.sp = self.sp.wrapping_add(1);
-        let pc_lo = mem[self.sp as u16 + 0x100];
-        self.sp = self.sp.wrapping_add(1);
-        let pc_hi = mem[self.sp as u16 + 0x100];
-        self.pc = u16::from_le_bytes([pc_lo, pc_hi]);
-        self.cycles += 4;
-    }
-    fn RTS(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
-        self.cycles += 4;
-        self.sp = self.sp.wrapping_add(1);
-        let pc_lo = mem[self.sp as u16 + 0x100];
-        self.sp = self.sp.wrapping_add(1);
-        let pc_hi = mem[self.sp as u16 + 0x100];
-        self.pc = u16::from_le_bytes([pc_lo, pc_hi]);
-        self.pc = self.pc.wrapping_add(1);
-    }
-    fn PHP(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
-        self.StackPush(mem, self.s.get() | 0x10);
-        self.cycles += 1;
-    }
-    fn PLP(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
-        self.sp = self.sp.wrapping_add(1);
-        let sp: u16 = self.sp as u16 + 0x100;
-        let p = mem[sp];
+    fn PLP(&mut self, p: u8) {
         self.s.set((p | 0x20) & 0xEF);
-        self.cycles += 2;
     }
-    fn PHA(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
-        self.StackPush(mem,self.a);
-        self.cycles += 1;
+    fn PHA(&mut self) -> u8 {
+        self.a
     }
-    fn PLA(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
-        let sp = self.sp as u16 + 0x100;
-        let a = mem[sp + 1];
-        self.a = a;
-        self.sp = self.sp.wrapping_add(1);
-        self.set_flags_z_n(a);
-        self.cycles += 2;
+    fn PLA(&mut self, m: u8) {
+        self.a = m;
+        self.set_flags_z_n(m);
     }
-    fn DEY(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
+    fn DEY(&mut self) {
         let y = self.y.wrapping_sub(1);
         self.y = y;
         self.set_flags_z_n(y);
     }
-    fn TAY(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
+    fn TAY(&mut self) {
         let a = self.a;
         self.y = a;
         self.set_flags_z_n(a);
     }
-    fn INY(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
+    fn INY(&mut self) {
         let y = self.y.wrapping_add(1);
         self.y = y;
         self.set_flags_z_n(y);
     }
-    fn INX(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
+    fn INX(&mut self) {
         let x = self.x.wrapping_add(1);
         self.x = x;
         self.set_flags_z_n(x);
     }
-    fn CLC(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
+    fn CLC(&mut self) {
         self.s.set_carry(false);
     }
-    fn SEC(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
+    fn SEC(&mut self) {
         self.s.set_carry(true);
     }
-    fn CLI(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
+    fn CLI(&mut self) {
         self.s.set_interrupt(false);
     }
-    fn SEI(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
+    fn SEI(&mut self) {
         self.s.set_interrupt(true);
     }
-    fn TYA(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
+    fn TYA(&mut self) {
         let y = self.y;
         self.a = y;
         self.set_flags_z_n(y);
     }
-    fn CLV(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
+    fn CLV(&mut self) {
         self.s.set_overflow(false);
     }
-    fn CLD(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
+    fn CLD(&mut self) {
         self.s.set_decimal(false);
     }
-    fn SED(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
+    fn SED(&mut self) {
         self.s.set_decimal(true);
     }
-    fn TXA(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
+    fn TXA(&mut self) {
         let x = self.x;
         self.a = x;
         self.set_flags_z_n(x);
     }
-    fn TXS(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
+    fn TXS(&mut self) {
         self.sp = self.x;
     }
-    fn TAX(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
+    fn TAX(&mut self) {
         let a = self.a;
         self.x = a;
         self.set_flags_z_n(a);
     }
-    fn TSX(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
+    fn TSX(&mut self) {
         let s = self.sp;
         self.x = s;
         self.set_flags_z_n(s);
     }
-    fn DEX(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
+    fn DEX(&mut self) {
         let x = self.x.wrapping_sub(1);
         self.x = x;
         self.set_flags_z_n(x);
     }
-    fn NOP(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {}
-    fn SLO(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
-        let addr = self.addr.unwrap();
-        let m2 = mem[addr];
-        let m = mem[addr] << 1;
-        self.a |= m;
-        mem[addr] = m;
-        self.set_flags_z_n_c(self.a, m2 & 0x80 == 0x80);
-    }
-    fn ANC(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
-        let addr = self.addr.unwrap();
-        let m = mem[addr];
+    fn NOP(&mut self) {}
+    //NOP that still reads its operand
+    fn IGN(&mut self, _m: u8) {}
+    fn SLO(&mut self, m: u8) -> u8 {
+        let res = m << 1;
+        self.a |= res;
+        self.set_flags_z_n_c(self.a, m & 0x80 == 0x80);
+        res
+    }
+    fn ANC(&mut self, m: u8) {
         self.a &= m;
         self.set_flags_z_n_c(self.a, self.a & 0x80 == 0x80);
     }
-    fn RLA(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>) {
-        let addr = self.addr.unwrap();
-        let m = mem[addr];
-        let m2 = m;
-        let (m, _) = m.overflowing_mul(2);
-        let (m, _) = m.overflowing_add(self.s.get_carry() as u8);
-        mem[addr] = m;
-        self.a &= m;
-        self.set_flags_z_n_c(self.a, m2 & 0x80 == 0x80);
-    }
-    fn SRE(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>){
-        let addr = self.addr.unwrap();
-        let m2 = mem[addr];
-        let m = mem[addr] >> 1;
-        self.a ^= m;
-        mem[addr] = m;
-        self.set_flags_z_n_c(self.a, m2 & 0x01 == 0x01);
-    }
-    fn ALR(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>){
-        let imm = mem[self.addr.unwrap()];
-        let imm2 = imm;
+    fn RLA(&mut self, m: u8) -> u8 {
+        let res = self.ROL(m);
+        self.a &= res;
+        self.set_flags_z_n(self.a);
+        res
+    }
+    fn SRE(&mut self, m: u8) -> u8 {
+        let res = m >> 1;
+        self.a ^= res;
+        self.set_flags_z_n_c(self.a, m & 0x01 == 0x01);
+        res
+    }
+    fn ALR(&mut self, m: u8) {
         let a = self.a;
-        self.a &= imm;
-        self.a = self.a >> 1;
-        self.set_flags_z_n_c(self.a,(a & imm) & 0x01 == 0x01);
-    }
-    fn RRA(&mut self, mem: &mut dyn IndexMut<u16, Output = u8>){
-        let addr = self.addr.unwrap();
-        let m2 = mem[addr];
-        let underflow = (m2 & 0x01) << 7;
-        let m = underflow | (mem[addr] >> 1);
-        let tmp = m as u16 + self.a as u16  + self.s.get_carry() as u16;
-        mem[addr] = m;
-        self.set_flags_z_n_c(self.a, m2 & 0x01 == 0x01);
+        self.a &= m;
+        self.a >>= 1;
+        self.set_flags_z_n_c(self.a, (a & m) & 0x01 == 0x01);
+    }
+    fn RRA(&mut self, m: u8) -> u8 {
+        let res = self.ROR(m);
+        self.ADC(res);
+        res
+    }
+    fn SAX(&mut self) -> u8 {
+        self.a & self.x
+    }
+    fn LAX(&mut self, m: u8) {
+        self.a = m;
+        self.x = m;
+        self.set_flags_z_n(m);
+    }
+    fn DCP(&mut self, m: u8) -> u8 {
+        let res = m.wrapping_sub(1);
+        self.CMP(res);
+        res
+    }
+    fn ISC(&mut self, m: u8) -> u8 {
+        let res = m.wrapping_add(1);
+        self.SBC(res);
+        res
+    }
+    fn ARR(&mut self, m: u8) {
+        if self.decimal_mode() {
+            return self.arr_decimal(m);
+        }
+        self.a = ((self.a & m) >> 1) | ((self.s.get_carry() as u8) << 7);
+        self.set_flags_z_n(self.a);
+        self.s.set_carry(self.a & 0x40 == 0x40);
+        self.s.set_overflow(((self.a >> 6) ^ (self.a >> 5)) & 1 == 1);
+    }
+    fn SBX(&mut self, m: u8) {
+        let (res, o) = (self.a & self.x).overflowing_sub(m);
+        self.x = res;
+        self.set_flags_z_n_c(res, !o);
+    }
+    fn LAS(&mut self, m: u8) {
+        let res = m & self.sp;
+        self.a = res;
+        self.x = res;
+        self.sp = res;
+        self.set_flags_z_n(res);
+    }
+    fn XAA(&mut self, m: u8) {
+        self.a = (self.a | self.quirks.xaa_magic) & self.x & m;
+        self.set_flags_z_n(self.a);
+    }
+    fn LXA(&mut self, m: u8) {
+        let res = (self.a | self.quirks.lxa_magic) & m;
+        self.a = res;
+        self.x = res;
+        self.set_flags_z_n(res);
+    }
+    // The SH* stores AND the register with the high byte of the base
+    // address plus one. When the index carries, that value also replaces
+    // the high byte of the address written.
+    fn unstable_store(&mut self, val: u8) -> u8 {
+        let res = if self.quirks.sh_and_high {
+            val & ((self.base >> 8) as u8).wrapping_add(1)
+        } else {
+            val
+        };
+        if self.quirks.sh_corrupt_address && (self.base ^ self.addr) & 0xFF00 != 0 {
+            self.addr = (self.addr & 0x00FF) | ((res as u16) << 8);
+        }
+        res
+    }
+    fn SHA(&mut self) -> u8 {
+        self.unstable_store(self.a & self.x)
+    }
+    fn SHX(&mut self) -> u8 {
+        self.unstable_store(self.x)
+    }
+    fn SHY(&mut self) -> u8 {
+        self.unstable_store(self.y)
+    }
+    fn TAS(&mut self) -> u8 {
+        self.sp = self.a & self.x;
+        self.unstable_store(self.sp)
     }
     fn set_flags_z_n(&mut self, res: u8) {
         self.s.set_zero(res == 0);
@@ -941,11 +909,6 @@ impl Cpu {
         self.set_flags_z_n(res);
         self.s.set_carry(o);
     }
-    fn set_flags_z_n_c_o(&mut self, res: u8, o: bool, c: bool) {
-        self.set_flags_z_n(res);
-        self.s.set_overflow(o);
-        self.s.set_carry(c);
-    }
     fn set_flags_z_n_o(&mut self, res: u8, m: u8) {
         self.s.set_zero(res == 0);
         self.s.set_negative(m & 0x80 == 0x80);
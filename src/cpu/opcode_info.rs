@@ -0,0 +1,622 @@
+use super::Variant;
+use AddrMode::*;
+
+/// How an instruction forms its operand, as tooling sees it.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AddrMode {
+    Implied,
+    Accumulator,
+    Immediate,
+    ZeroPage,
+    ZeroPageX,
+    ZeroPageY,
+    Absolute,
+    AbsoluteX,
+    AbsoluteY,
+    /// `JMP ($nnnn)`
+    Indirect,
+    /// `($nn,X)`
+    IndirectX,
+    /// `($nn),Y`
+    IndirectY,
+    /// 65C02 `($nn)`
+    ZeroPageIndirect,
+    /// 65C02 `JMP ($nnnn,X)`
+    AbsoluteIndirectX,
+    /// Branches. A taken branch costs one more cycle.
+    Relative,
+    /// BBR/BBS: a zero page operand followed by a branch offset.
+    ZeroPageRelative,
+}
+
+impl AddrMode {
+    /// Operand bytes following the opcode.
+    pub const fn operand_len(self) -> u8 {
+        match self {
+            Implied | Accumulator => 0,
+            Absolute | AbsoluteX | AbsoluteY | Indirect | AbsoluteIndirectX | ZeroPageRelative => 2,
+            _ => 1,
+        }
+    }
+}
+
+/// Static description of one opcode.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct OpcodeInfo {
+    pub mnemonic: &'static str,
+    pub mode: AddrMode,
+    /// Instruction length in bytes, opcode included.
+    pub len: u8,
+    /// Cycles without page-cross, branch or decimal penalties.
+    pub cycles: u8,
+    /// Whether a page cross costs a cycle: indexed reads whose address
+    /// carries, and taken branches that land on another page.
+    pub page_cross: bool,
+    /// Whether the opcode is documented for this CPU.
+    pub official: bool,
+    /// Mask of the P bits the instruction can change.
+    pub flags: u8,
+}
+
+const N: u8 = 0x80;
+const V: u8 = 0x40;
+const D: u8 = 0x08;
+const I: u8 = 0x04;
+const Z: u8 = 0x02;
+const C: u8 = 0x01;
+
+const fn op(mnemonic: &'static str, mode: AddrMode, cycles: u8, flags: u8) -> OpcodeInfo {
+    OpcodeInfo {
+        mnemonic,
+        mode,
+        len: 1 + mode.operand_len(),
+        cycles,
+        page_cross: false,
+        official: true,
+        flags,
+    }
+}
+
+impl OpcodeInfo {
+    const fn illegal(self) -> OpcodeInfo {
+        OpcodeInfo { official: false, ..self }
+    }
+    const fn page_cross(self) -> OpcodeInfo {
+        OpcodeInfo { page_cross: true, ..self }
+    }
+}
+
+/// The opcode table for `variant`. The 65C02 without the bit instructions
+/// treats their slots, and those of WAI and STP, as one-cycle NOPs.
+pub fn opcode_info(variant: Variant, opcode: u8) -> &'static OpcodeInfo {
+    if !variant.is_cmos() {
+        return &NMOS_OPCODES[opcode as usize];
+    }
+    let bit_op = matches!(opcode & 0x0F, 0x07 | 0x0F) || matches!(opcode, 0xCB | 0xDB);
+    if bit_op && !variant.has_bit_instructions() {
+        return &CMOS_NOP1;
+    }
+    &CMOS_OPCODES[opcode as usize]
+}
+
+static CMOS_NOP1: OpcodeInfo = op("NOP", Implied, 1, 0).illegal();
+
+/// NMOS 6502 and 2A03 opcodes, including the undocumented ones.
+pub static NMOS_OPCODES: [OpcodeInfo; 256] = [
+    op("BRK", Implied, 7, I), // $00
+    op("ORA", IndirectX, 6, N | Z), // $01
+    op("JAM", Implied, 2, 0).illegal(), // $02
+    op("SLO", IndirectX, 8, N | Z | C).illegal(), // $03
+    op("NOP", ZeroPage, 3, 0).illegal(), // $04
+    op("ORA", ZeroPage, 3, N | Z), // $05
+    op("ASL", ZeroPage, 5, N | Z | C), // $06
+    op("SLO", ZeroPage, 5, N | Z | C).illegal(), // $07
+    op("PHP", Implied, 3, 0), // $08
+    op("ORA", Immediate, 2, N | Z), // $09
+    op("ASL", Accumulator, 2, N | Z | C), // $0A
+    op("ANC", Immediate, 2, N | Z | C).illegal(), // $0B
+    op("NOP", Absolute, 4, 0).illegal(), // $0C
+    op("ORA", Absolute, 4, N | Z), // $0D
+    op("ASL", Absolute, 6, N | Z | C), // $0E
+    op("SLO", Absolute, 6, N | Z | C).illegal(), // $0F
+    op("BPL", Relative, 2, 0).page_cross(), // $10
+    op("ORA", IndirectY, 5, N | Z).page_cross(), // $11
+    op("JAM", Implied, 2, 0).illegal(), // $12
+    op("SLO", IndirectY, 8, N | Z | C).illegal(), // $13
+    op("NOP", ZeroPageX, 4, 0).illegal(), // $14
+    op("ORA", ZeroPageX, 4, N | Z), // $15
+    op("ASL", ZeroPageX, 6, N | Z | C), // $16
+    op("SLO", ZeroPageX, 6, N | Z | C).illegal(), // $17
+    op("CLC", Implied, 2, C), // $18
+    op("ORA", AbsoluteY, 4, N | Z).page_cross(), // $19
+    op("NOP", Implied, 2, 0).illegal(), // $1A
+    op("SLO", AbsoluteY, 7, N | Z | C).illegal(), // $1B
+    op("NOP", AbsoluteX, 4, 0).illegal().page_cross(), // $1C
+    op("ORA", AbsoluteX, 4, N | Z).page_cross(), // $1D
+    op("ASL", AbsoluteX, 7, N | Z | C), // $1E
+    op("SLO", AbsoluteX, 7, N | Z | C).illegal(), // $1F
+    op("JSR", Absolute, 6, 0), // $20
+    op("AND", IndirectX, 6, N | Z), // $21
+    op("JAM", Implied, 2, 0).illegal(), // $22
+    op("RLA", IndirectX, 8, N | Z | C).illegal(), // $23
+    op("BIT", ZeroPage, 3, N | V | Z), // $24
+    op("AND", ZeroPage, 3, N | Z), // $25
+    op("ROL", ZeroPage, 5, N | Z | C), // $26
+    op("RLA", ZeroPage, 5, N | Z | C).illegal(), // $27
+    op("PLP", Implied, 4, N | V | D | I | Z | C), // $28
+    op("AND", Immediate, 2, N | Z), // $29
+    op("ROL", Accumulator, 2, N | Z | C), // $2A
+    op("ANC", Immediate, 2, N | Z | C).illegal(), // $2B
+    op("BIT", Absolute, 4, N | V | Z), // $2C
+    op("AND", Absolute, 4, N | Z), // $2D
+    op("ROL", Absolute, 6, N | Z | C), // $2E
+    op("RLA", Absolute, 6, N | Z | C).illegal(), // $2F
+    op("BMI", Relative, 2, 0).page_cross(), // $30
+    op("AND", IndirectY, 5, N | Z).page_cross(), // $31
+    op("JAM", Implied, 2, 0).illegal(), // $32
+    op("RLA", IndirectY, 8, N | Z | C).illegal(), // $33
+    op("NOP", ZeroPageX, 4, 0).illegal(), // $34
+    op("AND", ZeroPageX, 4, N | Z), // $35
+    op("ROL", ZeroPageX, 6, N | Z | C), // $36
+    op("RLA", ZeroPageX, 6, N | Z | C).illegal(), // $37
+    op("SEC", Implied, 2, C), // $38
+    op("AND", AbsoluteY, 4, N | Z).page_cross(), // $39
+    op("NOP", Implied, 2, 0).illegal(), // $3A
+    op("RLA", AbsoluteY, 7, N | Z | C).illegal(), // $3B
+    op("NOP", AbsoluteX, 4, 0).illegal().page_cross(), // $3C
+    op("AND", AbsoluteX, 4, N | Z).page_cross(), // $3D
+    op("ROL", AbsoluteX, 7, N | Z | C), // $3E
+    op("RLA", AbsoluteX, 7, N | Z | C).illegal(), // $3F
+    op("RTI", Implied, 6, N | V | D | I | Z | C), // $40
+    op("EOR", IndirectX, 6, N | Z), // $41
+    op("JAM", Implied, 2, 0).illegal(), // $42
+    op("SRE", IndirectX, 8, N | Z | C).illegal(), // $43
+    op("NOP", ZeroPage, 3, 0).illegal(), // $44
+    op("EOR", ZeroPage, 3, N | Z), // $45
+    op("LSR", ZeroPage, 5, N | Z | C), // $46
+    op("SRE", ZeroPage, 5, N | Z | C).illegal(), // $47
+    op("PHA", Implied, 3, 0), // $48
+    op("EOR", Immediate, 2, N | Z), // $49
+    op("LSR", Accumulator, 2, N | Z | C), // $4A
+    op("ALR", Immediate, 2, N | Z | C).illegal(), // $4B
+    op("JMP", Absolute, 3, 0), // $4C
+    op("EOR", Absolute, 4, N | Z), // $4D
+    op("LSR", Absolute, 6, N | Z | C), // $4E
+    op("SRE", Absolute, 6, N | Z | C).illegal(), // $4F
+    op("BVC", Relative, 2, 0).page_cross(), // $50
+    op("EOR", IndirectY, 5, N | Z).page_cross(), // $51
+    op("JAM", Implied, 2, 0).illegal(), // $52
+    op("SRE", IndirectY, 8, N | Z | C).illegal(), // $53
+    op("NOP", ZeroPageX, 4, 0).illegal(), // $54
+    op("EOR", ZeroPageX, 4, N | Z), // $55
+    op("LSR", ZeroPageX, 6, N | Z | C), // $56
+    op("SRE", ZeroPageX, 6, N | Z | C).illegal(), // $57
+    op("CLI", Implied, 2, I), // $58
+    op("EOR", AbsoluteY, 4, N | Z).page_cross(), // $59
+    op("NOP", Implied, 2, 0).illegal(), // $5A
+    op("SRE", AbsoluteY, 7, N | Z | C).illegal(), // $5B
+    op("NOP", AbsoluteX, 4, 0).illegal().page_cross(), // $5C
+    op("EOR", AbsoluteX, 4, N | Z).page_cross(), // $5D
+    op("LSR", AbsoluteX, 7, N | Z | C), // $5E
+    op("SRE", AbsoluteX, 7, N | Z | C).illegal(), // $5F
+    op("RTS", Implied, 6, 0), // $60
+    op("ADC", IndirectX, 6, N | V | Z | C), // $61
+    op("JAM", Implied, 2, 0).illegal(), // $62
+    op("RRA", IndirectX, 8, N | V | Z | C).illegal(), // $63
+    op("NOP", ZeroPage, 3, 0).illegal(), // $64
+    op("ADC", ZeroPage, 3, N | V | Z | C), // $65
+    op("ROR", ZeroPage, 5, N | Z | C), // $66
+    op("RRA", ZeroPage, 5, N | V | Z | C).illegal(), // $67
+    op("PLA", Implied, 4, N | Z), // $68
+    op("ADC", Immediate, 2, N | V | Z | C), // $69
+    op("ROR", Accumulator, 2, N | Z | C), // $6A
+    op("ARR", Immediate, 2, N | V | Z | C).illegal(), // $6B
+    op("JMP", Indirect, 5, 0), // $6C
+    op("ADC", Absolute, 4, N | V | Z | C), // $6D
+    op("ROR", Absolute, 6, N | Z | C), // $6E
+    op("RRA", Absolute, 6, N | V | Z | C).illegal(), // $6F
+    op("BVS", Relative, 2, 0).page_cross(), // $70
+    op("ADC", IndirectY, 5, N | V | Z | C).page_cross(), // $71
+    op("JAM", Implied, 2, 0).illegal(), // $72
+    op("RRA", IndirectY, 8, N | V | Z | C).illegal(), // $73
+    op("NOP", ZeroPageX, 4, 0).illegal(), // $74
+    op("ADC", ZeroPageX, 4, N | V | Z | C), // $75
+    op("ROR", ZeroPageX, 6, N | Z | C), // $76
+    op("RRA", ZeroPageX, 6, N | V | Z | C).illegal(), // $77
+    op("SEI", Implied, 2, I), // $78
+    op("ADC", AbsoluteY, 4, N | V | Z | C).page_cross(), // $79
+    op("NOP", Implied, 2, 0).illegal(), // $7A
+    op("RRA", AbsoluteY, 7, N | V | Z | C).illegal(), // $7B
+    op("NOP", AbsoluteX, 4, 0).illegal().page_cross(), // $7C
+    op("ADC", AbsoluteX, 4, N | V | Z | C).page_cross(), // $7D
+    op("ROR", AbsoluteX, 7, N | Z | C), // $7E
+    op("RRA", AbsoluteX, 7, N | V | Z | C).illegal(), // $7F
+    op("NOP", Immediate, 2, 0).illegal(), // $80
+    op("STA", IndirectX, 6, 0), // $81
+    op("NOP", Immediate, 2, 0).illegal(), // $82
+    op("SAX", IndirectX, 6, 0).illegal(), // $83
+    op("STY", ZeroPage, 3, 0), // $84
+    op("STA", ZeroPage, 3, 0), // $85
+    op("STX", ZeroPage, 3, 0), // $86
+    op("SAX", ZeroPage, 3, 0).illegal(), // $87
+    op("DEY", Implied, 2, N | Z), // $88
+    op("NOP", Immediate, 2, 0).illegal(), // $89
+    op("TXA", Implied, 2, N | Z), // $8A
+    op("XAA", Immediate, 2, N | Z).illegal(), // $8B
+    op("STY", Absolute, 4, 0), // $8C
+    op("STA", Absolute, 4, 0), // $8D
+    op("STX", Absolute, 4, 0), // $8E
+    op("SAX", Absolute, 4, 0).illegal(), // $8F
+    op("BCC", Relative, 2, 0).page_cross(), // $90
+    op("STA", IndirectY, 6, 0), // $91
+    op("JAM", Implied, 2, 0).illegal(), // $92
+    op("SHA", IndirectY, 6, 0).illegal(), // $93
+    op("STY", ZeroPageX, 4, 0), // $94
+    op("STA", ZeroPageX, 4, 0), // $95
+    op("STX", ZeroPageY, 4, 0), // $96
+    op("SAX", ZeroPageY, 4, 0).illegal(), // $97
+    op("TYA", Implied, 2, N | Z), // $98
+    op("STA", AbsoluteY, 5, 0), // $99
+    op("TXS", Implied, 2, 0), // $9A
+    op("TAS", AbsoluteY, 5, 0).illegal(), // $9B
+    op("SHY", AbsoluteX, 5, 0).illegal(), // $9C
+    op("STA", AbsoluteX, 5, 0), // $9D
+    op("SHX", AbsoluteY, 5, 0).illegal(), // $9E
+    op("SHA", AbsoluteY, 5, 0).illegal(), // $9F
+    op("LDY", Immediate, 2, N | Z), // $A0
+    op("LDA", IndirectX, 6, N | Z), // $A1
+    op("LDX", Immediate, 2, N | Z), // $A2
+    op("LAX", IndirectX, 6, N | Z).illegal(), // $A3
+    op("LDY", ZeroPage, 3, N | Z), // $A4
+    op("LDA", ZeroPage, 3, N | Z), // $A5
+    op("LDX", ZeroPage, 3, N | Z), // $A6
+    op("LAX", ZeroPage, 3, N | Z).illegal(), // $A7
+    op("TAY", Implied, 2, N | Z), // $A8
+    op("LDA", Immediate, 2, N | Z), // $A9
+    op("TAX", Implied, 2, N | Z), // $AA
+    op("LXA", Immediate, 2, N | Z).illegal(), // $AB
+    op("LDY", Absolute, 4, N | Z), // $AC
+    op("LDA", Absolute, 4, N | Z), // $AD
+    op("LDX", Absolute, 4, N | Z), // $AE
+    op("LAX", Absolute, 4, N | Z).illegal(), // $AF
+    op("BCS", Relative, 2, 0).page_cross(), // $B0
+    op("LDA", IndirectY, 5, N | Z).page_cross(), // $B1
+    op("JAM", Implied, 2, 0).illegal(), // $B2
+    op("LAX", IndirectY, 5, N | Z).illegal().page_cross(), // $B3
+    op("LDY", ZeroPageX, 4, N | Z), // $B4
+    op("LDA", ZeroPageX, 4, N | Z), // $B5
+    op("LDX", ZeroPageY, 4, N | Z), // $B6
+    op("LAX", ZeroPageY, 4, N | Z).illegal(), // $B7
+    op("CLV", Implied, 2, V), // $B8
+    op("LDA", AbsoluteY, 4, N | Z).page_cross(), // $B9
+    op("TSX", Implied, 2, N | Z), // $BA
+    op("LAS", AbsoluteY, 4, N | Z).illegal().page_cross(), // $BB
+    op("LDY", AbsoluteX, 4, N | Z).page_cross(), // $BC
+    op("LDA", AbsoluteX, 4, N | Z).page_cross(), // $BD
+    op("LDX", AbsoluteY, 4, N | Z).page_cross(), // $BE
+    op("LAX", AbsoluteY, 4, N | Z).illegal().page_cross(), // $BF
+    op("CPY", Immediate, 2, N | Z | C), // $C0
+    op("CMP", IndirectX, 6, N | Z | C), // $C1
+    op("NOP", Immediate, 2, 0).illegal(), // $C2
+    op("DCP", IndirectX, 8, N | Z | C).illegal(), // $C3
+    op("CPY", ZeroPage, 3, N | Z | C), // $C4
+    op("CMP", ZeroPage, 3, N | Z | C), // $C5
+    op("DEC", ZeroPage, 5, N | Z), // $C6
+    op("DCP", ZeroPage, 5, N | Z | C).illegal(), // $C7
+    op("INY", Implied, 2, N | Z), // $C8
+    op("CMP", Immediate, 2, N | Z | C), // $C9
+    op("DEX", Implied, 2, N | Z), // $CA
+    op("SBX", Immediate, 2, N | Z | C).illegal(), // $CB
+    op("CPY", Absolute, 4, N | Z | C), // $CC
+    op("CMP", Absolute, 4, N | Z | C), // $CD
+    op("DEC", Absolute, 6, N | Z), // $CE
+    op("DCP", Absolute, 6, N | Z | C).illegal(), // $CF
+    op("BNE", Relative, 2, 0).page_cross(), // $D0
+    op("CMP", IndirectY, 5, N | Z | C).page_cross(), // $D1
+    op("JAM", Implied, 2, 0).illegal(), // $D2
+    op("DCP", IndirectY, 8, N | Z | C).illegal(), // $D3
+    op("NOP", ZeroPageX, 4, 0).illegal(), // $D4
+    op("CMP", ZeroPageX, 4, N | Z | C), // $D5
+    op("DEC", ZeroPageX, 6, N | Z), // $D6
+    op("DCP", ZeroPageX, 6, N | Z | C).illegal(), // $D7
+    op("CLD", Implied, 2, D), // $D8
+    op("CMP", AbsoluteY, 4, N | Z | C).page_cross(), // $D9
+    op("NOP", Implied, 2, 0).illegal(), // $DA
+    op("DCP", AbsoluteY, 7, N | Z | C).illegal(), // $DB
+    op("NOP", AbsoluteX, 4, 0).illegal().page_cross(), // $DC
+    op("CMP", AbsoluteX, 4, N | Z | C).page_cross(), // $DD
+    op("DEC", AbsoluteX, 7, N | Z), // $DE
+    op("DCP", AbsoluteX, 7, N | Z | C).illegal(), // $DF
+    op("CPX", Immediate, 2, N | Z | C), // $E0
+    op("SBC", IndirectX, 6, N | V | Z | C), // $E1
+    op("NOP", Immediate, 2, 0).illegal(), // $E2
+    op("ISC", IndirectX, 8, N | V | Z | C).illegal(), // $E3
+    op("CPX", ZeroPage, 3, N | Z | C), // $E4
+    op("SBC", ZeroPage, 3, N | V | Z | C), // $E5
+    op("INC", ZeroPage, 5, N | Z), // $E6
+    op("ISC", ZeroPage, 5, N | V | Z | C).illegal(), // $E7
+    op("INX", Implied, 2, N | Z), // $E8
+    op("SBC", Immediate, 2, N | V | Z | C), // $E9
+    op("NOP", Implied, 2, 0), // $EA
+    op("SBC", Immediate, 2, N | V | Z | C).illegal(), // $EB
+    op("CPX", Absolute, 4, N | Z | C), // $EC
+    op("SBC", Absolute, 4, N | V | Z | C), // $ED
+    op("INC", Absolute, 6, N | Z), // $EE
+    op("ISC", Absolute, 6, N | V | Z | C).illegal(), // $EF
+    op("BEQ", Relative, 2, 0).page_cross(), // $F0
+    op("SBC", IndirectY, 5, N | V | Z | C).page_cross(), // $F1
+    op("JAM", Implied, 2, 0).illegal(), // $F2
+    op("ISC", IndirectY, 8, N | V | Z | C).illegal(), // $F3
+    op("NOP", ZeroPageX, 4, 0).illegal(), // $F4
+    op("SBC", ZeroPageX, 4, N | V | Z | C), // $F5
+    op("INC", ZeroPageX, 6, N | Z), // $F6
+    op("ISC", ZeroPageX, 6, N | V | Z | C).illegal(), // $F7
+    op("SED", Implied, 2, D), // $F8
+    op("SBC", AbsoluteY, 4, N | V | Z | C).page_cross(), // $F9
+    op("NOP", Implied, 2, 0).illegal(), // $FA
+    op("ISC", AbsoluteY, 7, N | V | Z | C).illegal(), // $FB
+    op("NOP", AbsoluteX, 4, 0).illegal().page_cross(), // $FC
+    op("SBC", AbsoluteX, 4, N | V | Z | C).page_cross(), // $FD
+    op("INC", AbsoluteX, 7, N | Z), // $FE
+    op("ISC", AbsoluteX, 7, N | V | Z | C).illegal(), // $FF
+];
+
+/// W65C02S opcodes. Undefined opcodes are NOPs of the listed length.
+pub static CMOS_OPCODES: [OpcodeInfo; 256] = [
+    op("BRK", Implied, 7, D | I), // $00
+    op("ORA", IndirectX, 6, N | Z), // $01
+    op("NOP", Immediate, 2, 0).illegal(), // $02
+    op("NOP", Implied, 1, 0).illegal(), // $03
+    op("TSB", ZeroPage, 5, Z), // $04
+    op("ORA", ZeroPage, 3, N | Z), // $05
+    op("ASL", ZeroPage, 5, N | Z | C), // $06
+    op("RMB0", ZeroPage, 5, 0), // $07
+    op("PHP", Implied, 3, 0), // $08
+    op("ORA", Immediate, 2, N | Z), // $09
+    op("ASL", Accumulator, 2, N | Z | C), // $0A
+    op("NOP", Implied, 1, 0).illegal(), // $0B
+    op("TSB", Absolute, 6, Z), // $0C
+    op("ORA", Absolute, 4, N | Z), // $0D
+    op("ASL", Absolute, 6, N | Z | C), // $0E
+    op("BBR0", ZeroPageRelative, 5, 0).page_cross(), // $0F
+    op("BPL", Relative, 2, 0).page_cross(), // $10
+    op("ORA", IndirectY, 5, N | Z).page_cross(), // $11
+    op("ORA", ZeroPageIndirect, 5, N | Z), // $12
+    op("NOP", Implied, 1, 0).illegal(), // $13
+    op("TRB", ZeroPage, 5, Z), // $14
+    op("ORA", ZeroPageX, 4, N | Z), // $15
+    op("ASL", ZeroPageX, 6, N | Z | C), // $16
+    op("RMB1", ZeroPage, 5, 0), // $17
+    op("CLC", Implied, 2, C), // $18
+    op("ORA", AbsoluteY, 4, N | Z).page_cross(), // $19
+    op("INC", Accumulator, 2, N | Z), // $1A
+    op("NOP", Implied, 1, 0).illegal(), // $1B
+    op("TRB", Absolute, 6, Z), // $1C
+    op("ORA", AbsoluteX, 4, N | Z).page_cross(), // $1D
+    op("ASL", AbsoluteX, 6, N | Z | C).page_cross(), // $1E
+    op("BBR1", ZeroPageRelative, 5, 0).page_cross(), // $1F
+    op("JSR", Absolute, 6, 0), // $20
+    op("AND", IndirectX, 6, N | Z), // $21
+    op("NOP", Immediate, 2, 0).illegal(), // $22
+    op("NOP", Implied, 1, 0).illegal(), // $23
+    op("BIT", ZeroPage, 3, N | V | Z), // $24
+    op("AND", ZeroPage, 3, N | Z), // $25
+    op("ROL", ZeroPage, 5, N | Z | C), // $26
+    op("RMB2", ZeroPage, 5, 0), // $27
+    op("PLP", Implied, 4, N | V | D | I | Z | C), // $28
+    op("AND", Immediate, 2, N | Z), // $29
+    op("ROL", Accumulator, 2, N | Z | C), // $2A
+    op("NOP", Implied, 1, 0).illegal(), // $2B
+    op("BIT", Absolute, 4, N | V | Z), // $2C
+    op("AND", Absolute, 4, N | Z), // $2D
+    op("ROL", Absolute, 6, N | Z | C), // $2E
+    op("BBR2", ZeroPageRelative, 5, 0).page_cross(), // $2F
+    op("BMI", Relative, 2, 0).page_cross(), // $30
+    op("AND", IndirectY, 5, N | Z).page_cross(), // $31
+    op("AND", ZeroPageIndirect, 5, N | Z), // $32
+    op("NOP", Implied, 1, 0).illegal(), // $33
+    op("BIT", ZeroPageX, 4, N | V | Z), // $34
+    op("AND", ZeroPageX, 4, N | Z), // $35
+    op("ROL", ZeroPageX, 6, N | Z | C), // $36
+    op("RMB3", ZeroPage, 5, 0), // $37
+    op("SEC", Implied, 2, C), // $38
+    op("AND", AbsoluteY, 4, N | Z).page_cross(), // $39
+    op("DEC", Accumulator, 2, N | Z), // $3A
+    op("NOP", Implied, 1, 0).illegal(), // $3B
+    op("BIT", AbsoluteX, 4, N | V | Z).page_cross(), // $3C
+    op("AND", AbsoluteX, 4, N | Z).page_cross(), // $3D
+    op("ROL", AbsoluteX, 6, N | Z | C).page_cross(), // $3E
+    op("BBR3", ZeroPageRelative, 5, 0).page_cross(), // $3F
+    op("RTI", Implied, 6, N | V | D | I | Z | C), // $40
+    op("EOR", IndirectX, 6, N | Z), // $41
+    op("NOP", Immediate, 2, 0).illegal(), // $42
+    op("NOP", Implied, 1, 0).illegal(), // $43
+    op("NOP", ZeroPage, 3, 0).illegal(), // $44
+    op("EOR", ZeroPage, 3, N | Z), // $45
+    op("LSR", ZeroPage, 5, N | Z | C), // $46
+    op("RMB4", ZeroPage, 5, 0), // $47
+    op("PHA", Implied, 3, 0), // $48
+    op("EOR", Immediate, 2, N | Z), // $49
+    op("LSR", Accumulator, 2, N | Z | C), // $4A
+    op("NOP", Implied, 1, 0).illegal(), // $4B
+    op("JMP", Absolute, 3, 0), // $4C
+    op("EOR", Absolute, 4, N | Z), // $4D
+    op("LSR", Absolute, 6, N | Z | C), // $4E
+    op("BBR4", ZeroPageRelative, 5, 0).page_cross(), // $4F
+    op("BVC", Relative, 2, 0).page_cross(), // $50
+    op("EOR", IndirectY, 5, N | Z).page_cross(), // $51
+    op("EOR", ZeroPageIndirect, 5, N | Z), // $52
+    op("NOP", Implied, 1, 0).illegal(), // $53
+    op("NOP", ZeroPageX, 4, 0).illegal(), // $54
+    op("EOR", ZeroPageX, 4, N | Z), // $55
+    op("LSR", ZeroPageX, 6, N | Z | C), // $56
+    op("RMB5", ZeroPage, 5, 0), // $57
+    op("CLI", Implied, 2, I), // $58
+    op("EOR", AbsoluteY, 4, N | Z).page_cross(), // $59
+    op("PHY", Implied, 3, 0), // $5A
+    op("NOP", Implied, 1, 0).illegal(), // $5B
+    op("NOP", Absolute, 8, 0).illegal(), // $5C
+    op("EOR", AbsoluteX, 4, N | Z).page_cross(), // $5D
+    op("LSR", AbsoluteX, 6, N | Z | C).page_cross(), // $5E
+    op("BBR5", ZeroPageRelative, 5, 0).page_cross(), // $5F
+    op("RTS", Implied, 6, 0), // $60
+    op("ADC", IndirectX, 6, N | V | Z | C), // $61
+    op("NOP", Immediate, 2, 0).illegal(), // $62
+    op("NOP", Implied, 1, 0).illegal(), // $63
+    op("STZ", ZeroPage, 3, 0), // $64
+    op("ADC", ZeroPage, 3, N | V | Z | C), // $65
+    op("ROR", ZeroPage, 5, N | Z | C), // $66
+    op("RMB6", ZeroPage, 5, 0), // $67
+    op("PLA", Implied, 4, N | Z), // $68
+    op("ADC", Immediate, 2, N | V | Z | C), // $69
+    op("ROR", Accumulator, 2, N | Z | C), // $6A
+    op("NOP", Implied, 1, 0).illegal(), // $6B
+    op("JMP", Indirect, 6, 0), // $6C
+    op("ADC", Absolute, 4, N | V | Z | C), // $6D
+    op("ROR", Absolute, 6, N | Z | C), // $6E
+    op("BBR6", ZeroPageRelative, 5, 0).page_cross(), // $6F
+    op("BVS", Relative, 2, 0).page_cross(), // $70
+    op("ADC", IndirectY, 5, N | V | Z | C).page_cross(), // $71
+    op("ADC", ZeroPageIndirect, 5, N | V | Z | C), // $72
+    op("NOP", Implied, 1, 0).illegal(), // $73
+    op("STZ", ZeroPageX, 4, 0), // $74
+    op("ADC", ZeroPageX, 4, N | V | Z | C), // $75
+    op("ROR", ZeroPageX, 6, N | Z | C), // $76
+    op("RMB7", ZeroPage, 5, 0), // $77
+    op("SEI", Implied, 2, I), // $78
+    op("ADC", AbsoluteY, 4, N | V | Z | C).page_cross(), // $79
+    op("PLY", Implied, 4, N | Z), // $7A
+    op("NOP", Implied, 1, 0).illegal(), // $7B
+    op("JMP", AbsoluteIndirectX, 6, 0), // $7C
+    op("ADC", AbsoluteX, 4, N | V | Z | C).page_cross(), // $7D
+    op("ROR", AbsoluteX, 6, N | Z | C).page_cross(), // $7E
+    op("BBR7", ZeroPageRelative, 5, 0).page_cross(), // $7F
+    op("BRA", Relative, 3, 0).page_cross(), // $80
+    op("STA", IndirectX, 6, 0), // $81
+    op("NOP", Immediate, 2, 0).illegal(), // $82
+    op("NOP", Implied, 1, 0).illegal(), // $83
+    op("STY", ZeroPage, 3, 0), // $84
+    op("STA", ZeroPage, 3, 0), // $85
+    op("STX", ZeroPage, 3, 0), // $86
+    op("SMB0", ZeroPage, 5, 0), // $87
+    op("DEY", Implied, 2, N | Z), // $88
+    op("BIT", Immediate, 2, Z), // $89
+    op("TXA", Implied, 2, N | Z), // $8A
+    op("NOP", Implied, 1, 0).illegal(), // $8B
+    op("STY", Absolute, 4, 0), // $8C
+    op("STA", Absolute, 4, 0), // $8D
+    op("STX", Absolute, 4, 0), // $8E
+    op("BBS0", ZeroPageRelative, 5, 0).page_cross(), // $8F
+    op("BCC", Relative, 2, 0).page_cross(), // $90
+    op("STA", IndirectY, 6, 0), // $91
+    op("STA", ZeroPageIndirect, 5, 0), // $92
+    op("NOP", Implied, 1, 0).illegal(), // $93
+    op("STY", ZeroPageX, 4, 0), // $94
+    op("STA", ZeroPageX, 4, 0), // $95
+    op("STX", ZeroPageY, 4, 0), // $96
+    op("SMB1", ZeroPage, 5, 0), // $97
+    op("TYA", Implied, 2, N | Z), // $98
+    op("STA", AbsoluteY, 5, 0), // $99
+    op("TXS", Implied, 2, 0), // $9A
+    op("NOP", Implied, 1, 0).illegal(), // $9B
+    op("STZ", Absolute, 4, 0), // $9C
+    op("STA", AbsoluteX, 5, 0), // $9D
+    op("STZ", AbsoluteX, 5, 0), // $9E
+    op("BBS1", ZeroPageRelative, 5, 0).page_cross(), // $9F
+    op("LDY", Immediate, 2, N | Z), // $A0
+    op("LDA", IndirectX, 6, N | Z), // $A1
+    op("LDX", Immediate, 2, N | Z), // $A2
+    op("NOP", Implied, 1, 0).illegal(), // $A3
+    op("LDY", ZeroPage, 3, N | Z), // $A4
+    op("LDA", ZeroPage, 3, N | Z), // $A5
+    op("LDX", ZeroPage, 3, N | Z), // $A6
+    op("SMB2", ZeroPage, 5, 0), // $A7
+    op("TAY", Implied, 2, N | Z), // $A8
+    op("LDA", Immediate, 2, N | Z), // $A9
+    op("TAX", Implied, 2, N | Z), // $AA
+    op("NOP", Implied, 1, 0).illegal(), // $AB
+    op("LDY", Absolute, 4, N | Z), // $AC
+    op("LDA", Absolute, 4, N | Z), // $AD
+    op("LDX", Absolute, 4, N | Z), // $AE
+    op("BBS2", ZeroPageRelative, 5, 0).page_cross(), // $AF
+    op("BCS", Relative, 2, 0).page_cross(), // $B0
+    op("LDA", IndirectY, 5, N | Z).page_cross(), // $B1
+    op("LDA", ZeroPageIndirect, 5, N | Z), // $B2
+    op("NOP", Implied, 1, 0).illegal(), // $B3
+    op("LDY", ZeroPageX, 4, N | Z), // $B4
+    op("LDA", ZeroPageX, 4, N | Z), // $B5
+    op("LDX", ZeroPageY, 4, N | Z), // $B6
+    op("SMB3", ZeroPage, 5, 0), // $B7
+    op("CLV", Implied, 2, V), // $B8
+    op("LDA", AbsoluteY, 4, N | Z).page_cross(), // $B9
+    op("TSX", Implied, 2, N | Z), // $BA
+    op("NOP", Implied, 1, 0).illegal(), // $BB
+    op("LDY", AbsoluteX, 4, N | Z).page_cross(), // $BC
+    op("LDA", AbsoluteX, 4, N | Z).page_cross(), // $BD
+    op("LDX", AbsoluteY, 4, N | Z).page_cross(), // $BE
+    op("BBS3", ZeroPageRelative, 5, 0).page_cross(), // $BF
+    op("CPY", Immediate, 2, N | Z | C), // $C0
+    op("CMP", IndirectX, 6, N | Z | C), // $C1
+    op("NOP", Immediate, 2, 0).illegal(), // $C2
+    op("NOP", Implied, 1, 0).illegal(), // $C3
+    op("CPY", ZeroPage, 3, N | Z | C), // $C4
+    op("CMP", ZeroPage, 3, N | Z | C), // $C5
+    op("DEC", ZeroPage, 5, N | Z), // $C6
+    op("SMB4", ZeroPage, 5, 0), // $C7
+    op("INY", Implied, 2, N | Z), // $C8
+    op("CMP", Immediate, 2, N | Z | C), // $C9
+    op("DEX", Implied, 2, N | Z), // $CA
+    op("WAI", Implied, 3, 0), // $CB
+    op("CPY", Absolute, 4, N | Z | C), // $CC
+    op("CMP", Absolute, 4, N | Z | C), // $CD
+    op("DEC", Absolute, 6, N | Z), // $CE
+    op("BBS4", ZeroPageRelative, 5, 0).page_cross(), // $CF
+    op("BNE", Relative, 2, 0).page_cross(), // $D0
+    op("CMP", IndirectY, 5, N | Z | C).page_cross(), // $D1
+    op("CMP", ZeroPageIndirect, 5, N | Z | C), // $D2
+    op("NOP", Implied, 1, 0).illegal(), // $D3
+    op("NOP", ZeroPageX, 4, 0).illegal(), // $D4
+    op("CMP", ZeroPageX, 4, N | Z | C), // $D5
+    op("DEC", ZeroPageX, 6, N | Z), // $D6
+    op("SMB5", ZeroPage, 5, 0), // $D7
+    op("CLD", Implied, 2, D), // $D8
+    op("CMP", AbsoluteY, 4, N | Z | C).page_cross(), // $D9
+    op("PHX", Implied, 3, 0), // $DA
+    op("STP", Implied, 3, 0), // $DB
+    op("NOP", Absolute, 4, 0).illegal(), // $DC
+    op("CMP", AbsoluteX, 4, N | Z | C).page_cross(), // $DD
+    op("DEC", AbsoluteX, 7, N | Z), // $DE
+    op("BBS5", ZeroPageRelative, 5, 0).page_cross(), // $DF
+    op("CPX", Immediate, 2, N | Z | C), // $E0
+    op("SBC", IndirectX, 6, N | V | Z | C), // $E1
+    op("NOP", Immediate, 2, 0).illegal(), // $E2
+    op("NOP", Implied, 1, 0).illegal(), // $E3
+    op("CPX", ZeroPage, 3, N | Z | C), // $E4
+    op("SBC", ZeroPage, 3, N | V | Z | C), // $E5
+    op("INC", ZeroPage, 5, N | Z), // $E6
+    op("SMB6", ZeroPage, 5, 0), // $E7
+    op("INX", Implied, 2, N | Z), // $E8
+    op("SBC", Immediate, 2, N | V | Z | C), // $E9
+    op("NOP", Implied, 2, 0), // $EA
+    op("NOP", Implied, 1, 0).illegal(), // $EB
+    op("CPX", Absolute, 4, N | Z | C), // $EC
+    op("SBC", Absolute, 4, N | V | Z | C), // $ED
+    op("INC", Absolute, 6, N | Z), // $EE
+    op("BBS6", ZeroPageRelative, 5, 0).page_cross(), // $EF
+    op("BEQ", Relative, 2, 0).page_cross(), // $F0
+    op("SBC", IndirectY, 5, N | V | Z | C).page_cross(), // $F1
+    op("SBC", ZeroPageIndirect, 5, N | V | Z | C), // $F2
+    op("NOP", Implied, 1, 0).illegal(), // $F3
+    op("NOP", ZeroPageX, 4, 0).illegal(), // $F4
+    op("SBC", ZeroPageX, 4, N | V | Z | C), // $F5
+    op("INC", ZeroPageX, 6, N | Z), // $F6
+    op("SMB7", ZeroPage, 5, 0), // $F7
+    op("SED", Implied, 2, D), // $F8
+    op("SBC", AbsoluteY, 4, N | V | Z | C).page_cross(), // $F9
+    op("PLX", Implied, 4, N | Z), // $FA
+    op("NOP", Implied, 1, 0).illegal(), // $FB
+    op("NOP", Absolute, 4, 0).illegal(), // $FC
+    op("SBC", AbsoluteX, 4, N | V | Z | C).page_cross(), // $FD
+    op("INC", AbsoluteX, 7, N | Z), // $FE
+    op("BBS7", ZeroPageRelative, 5, 0).page_cross(), // $FF
+];
@@ -0,0 +1,29 @@
+/// Behaviour of the unstable illegal opcodes, which varies between chips
+/// and even between runs on the same console.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CpuQuirks {
+    /// Constant ORed into A by XAA/ANE ($8B). Commonly $EE, $FF or $00.
+    pub xaa_magic: u8,
+    /// Constant ORed into A by LXA ($AB). Commonly $EE, $FF or $00.
+    pub lxa_magic: u8,
+    /// Whether SHA, SHX, SHY and TAS AND the stored value with the high
+    /// byte of the base address plus one. The term drops out when DMA or
+    /// RDY halts the CPU on the right cycle.
+    pub sh_and_high: bool,
+    /// Whether a page-crossing SHA, SHX, SHY or TAS replaces the high byte
+    /// of the target address with the value stored.
+    pub sh_corrupt_address: bool,
+}
+
+impl Default for CpuQuirks {
+    /// Matches the SingleStepTests expectations.
+    fn default() -> CpuQuirks {
+        CpuQuirks {
+            xaa_magic: 0xEE,
+            lxa_magic: 0xEE,
+            sh_and_high: true,
+            sh_corrupt_address: true,
+        }
+    }
+}
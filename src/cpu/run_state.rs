@@ -0,0 +1,25 @@
+/// Whether the core is executing, reported by `Cpu::run_instr` so a host
+/// loop can tell a halted CPU from a busy one.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RunState {
+    Running,
+    /// After WAI: idle until IRQ or NMI is asserted.
+    Waiting,
+    /// After STP: idle until reset.
+    Stopped,
+    /// After an NMOS JAM (KIL) opcode: the bus is locked until reset.
+    Jammed { addr: u16, opcode: u8 },
+    /// RESET is held: idle until it is released, which starts the reset
+    /// sequence.
+    Reset,
+}
+
+/// What `Cpu::run_instr` completed: the cycles it took and the state the
+/// CPU is left in, which is running, waiting or held in reset.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StepOutcome {
+    pub cycles: u64,
+    pub state: RunState,
+}
@@ -0,0 +1,361 @@
+use super::{Cpu, CpuQuirks, Instruction, Interrupt, IrqSources, Mode, Op, RunState, Variant};
+use core::fmt;
+
+/// Everything a `Cpu` needs to carry on exactly where it was saved,
+/// including a half-run instruction, latched interrupts and the cycle
+/// total. Taken with `Cpu::save_state` and put back with
+/// `Cpu::load_state`; `to_bytes` and `from_bytes` give a versioned binary
+/// form, and the `serde` feature adds `Serialize` and `Deserialize`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CpuState {
+    a: u8,
+    x: u8,
+    y: u8,
+    p: u8,
+    sp: u8,
+    pc: u16,
+    addr: u16,
+    cycles: u64,
+    instruction: u8,
+    quirks: CpuQuirks,
+    variant: Variant,
+    state: RunState,
+    step: u8,
+    base: u16,
+    data: u8,
+    irq_sources: IrqSources,
+    nmi_line: bool,
+    reset_line: bool,
+    reset_pending: bool,
+    nmi_edge: bool,
+    poll: bool,
+    prev_poll: bool,
+    interrupt: Option<Interrupt>,
+}
+
+/// Why `CpuState::from_bytes` or `Cpu::load_state` rejected a state.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StateError {
+    /// The buffer is shorter than `CpuState::SIZE`.
+    Truncated,
+    /// The data does not start with the save state magic.
+    BadMagic,
+    /// The data was written by a newer, unknown format version.
+    UnsupportedVersion(u8),
+    /// The fields hold values no CPU can be in.
+    Corrupt,
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            StateError::Truncated => write!(f, "save state is truncated"),
+            StateError::BadMagic => write!(f, "not a CPU save state"),
+            StateError::UnsupportedVersion(v) => write!(f, "unsupported save state version {}", v),
+            StateError::Corrupt => write!(f, "save state is corrupt"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for StateError {}
+
+const MAGIC: [u8; 4] = *b"6502";
+const VERSION: u8 = 1;
+
+// Bits of the line byte.
+const NMI_LINE: u8 = 1 << 0;
+const RESET_LINE: u8 = 1 << 1;
+const RESET_PENDING: u8 = 1 << 2;
+const NMI_EDGE: u8 = 1 << 3;
+const POLL: u8 = 1 << 4;
+const PREV_POLL: u8 = 1 << 5;
+// Bits of the quirk flag byte.
+const SH_AND_HIGH: u8 = 1 << 0;
+const SH_CORRUPT_ADDRESS: u8 = 1 << 1;
+
+impl CpuState {
+    /// Length of the binary form. Version 1 lays it out as the magic
+    /// `"6502"`, the version byte and then, little-endian: A, X, Y, P, SP,
+    /// PC, addr, cycles, opcode, step, base, data, variant, the quirks,
+    /// the run state, the IRQ sources, the line flags and the interrupt
+    /// being taken.
+    pub const SIZE: usize = 41;
+
+    /// The CPU's cycle total when the state was saved.
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    // The bus sequence and operation of the saved opcode, which are not
+    // stored themselves. Fails if the rest of the state cannot go with it.
+    fn decoded(&self) -> Result<(Mode, Op), StateError> {
+        let mut cpu = Cpu::new(None);
+        cpu.instruction = Instruction(self.instruction);
+        cpu.variant = self.variant;
+        let (mode, op) = cpu.decode();
+        // interrupts are taken by the BRK sequence, which leaves BRK as the
+        // opcode until it finishes
+        let interrupt_fits = self.interrupt.is_none() || (mode == Mode::Brk && self.step != 0);
+        let reset_fits = self.state != RunState::Reset || self.reset_line || self.reset_pending;
+        if self.step > mode.last_step() || !interrupt_fits || !reset_fits {
+            return Err(StateError::Corrupt);
+        }
+        Ok((mode, op))
+    }
+    /// The binary form of the state.
+    pub fn to_bytes(&self) -> [u8; CpuState::SIZE] {
+        let mut out = [0; CpuState::SIZE];
+        let mut w = Writer { buf: &mut out, pos: 0 };
+        w.bytes(&MAGIC);
+        w.u8(VERSION);
+        w.bytes(&[self.a, self.x, self.y, self.p, self.sp]);
+        w.u16(self.pc);
+        w.u16(self.addr);
+        w.bytes(&self.cycles.to_le_bytes());
+        w.bytes(&[self.instruction, self.step]);
+        w.u16(self.base);
+        w.u8(self.data);
+        w.u8(match self.variant {
+            Variant::Nmos6502 => 0,
+            Variant::Ricoh2A03 => 1,
+            Variant::Cmos65C02 => 2,
+            Variant::W65C02S => 3,
+        });
+        w.bytes(&[self.quirks.xaa_magic, self.quirks.lxa_magic]);
+        w.u8(flag(self.quirks.sh_and_high, SH_AND_HIGH) | flag(self.quirks.sh_corrupt_address, SH_CORRUPT_ADDRESS));
+        let (state, addr, opcode) = match self.state {
+            RunState::Running => (0, 0, 0),
+            RunState::Waiting => (1, 0, 0),
+            RunState::Stopped => (2, 0, 0),
+            RunState::Jammed { addr, opcode } => (3, addr, opcode),
+            RunState::Reset => (4, 0, 0),
+        };
+        w.u8(state);
+        w.u16(addr);
+        w.u8(opcode);
+        w.bytes(&self.irq_sources.bits().to_le_bytes());
+        w.u8(flag(self.nmi_line, NMI_LINE)
+            | flag(self.reset_line, RESET_LINE)
+            | flag(self.reset_pending, RESET_PENDING)
+            | flag(self.nmi_edge, NMI_EDGE)
+            | flag(self.poll, POLL)
+            | flag(self.prev_poll, PREV_POLL));
+        w.u8(match self.interrupt {
+            None => 0,
+            Some(Interrupt::Irq) => 1,
+            Some(Interrupt::Nmi) => 2,
+            Some(Interrupt::Reset) => 3,
+        });
+        debug_assert_eq!(w.pos, CpuState::SIZE);
+        out
+    }
+    /// Parses the binary form written by `to_bytes`. Bytes past
+    /// `CpuState::SIZE` are ignored, so a state can lead a larger file.
+    pub fn from_bytes(bytes: &[u8]) -> Result<CpuState, StateError> {
+        if bytes.len() < MAGIC.len() + 1 {
+            return Err(StateError::Truncated);
+        }
+        if bytes[..MAGIC.len()] != MAGIC {
+            return Err(StateError::BadMagic);
+        }
+        let version = bytes[MAGIC.len()];
+        if version != VERSION {
+            return Err(StateError::UnsupportedVersion(version));
+        }
+        if bytes.len() < CpuState::SIZE {
+            return Err(StateError::Truncated);
+        }
+        let mut r = Reader { buf: bytes, pos: MAGIC.len() + 1 };
+        let [a, x, y, p, sp] = [r.u8(), r.u8(), r.u8(), r.u8(), r.u8()];
+        let pc = r.u16();
+        let addr = r.u16();
+        let cycles = r.u64();
+        let instruction = r.u8();
+        let step = r.u8();
+        let base = r.u16();
+        let data = r.u8();
+        let variant = match r.u8() {
+            0 => Variant::Nmos6502,
+            1 => Variant::Ricoh2A03,
+            2 => Variant::Cmos65C02,
+            3 => Variant::W65C02S,
+            _ => return Err(StateError::Corrupt),
+        };
+        let (xaa_magic, lxa_magic, quirk_flags) = (r.u8(), r.u8(), r.u8());
+        if quirk_flags & !(SH_AND_HIGH | SH_CORRUPT_ADDRESS) != 0 {
+            return Err(StateError::Corrupt);
+        }
+        let quirks = CpuQuirks {
+            xaa_magic,
+            lxa_magic,
+            sh_and_high: quirk_flags & SH_AND_HIGH != 0,
+            sh_corrupt_address: quirk_flags & SH_CORRUPT_ADDRESS != 0,
+        };
+        let (tag, jam_addr, jam_opcode) = (r.u8(), r.u16(), r.u8());
+        let state = match tag {
+            0 => RunState::Running,
+            1 => RunState::Waiting,
+            2 => RunState::Stopped,
+            3 => RunState::Jammed { addr: jam_addr, opcode: jam_opcode },
+            4 => RunState::Reset,
+            _ => return Err(StateError::Corrupt),
+        };
+        let irq_sources = IrqSources::from_bits(r.u32());
+        let lines = r.u8();
+        if lines & !(NMI_LINE | RESET_LINE | RESET_PENDING | NMI_EDGE | POLL | PREV_POLL) != 0 {
+            return Err(StateError::Corrupt);
+        }
+        let interrupt = match r.u8() {
+            0 => None,
+            1 => Some(Interrupt::Irq),
+            2 => Some(Interrupt::Nmi),
+            3 => Some(Interrupt::Reset),
+            _ => return Err(StateError::Corrupt),
+        };
+        let state = CpuState {
+            a,
+            x,
+            y,
+            p,
+            sp,
+            pc,
+            addr,
+            cycles,
+            instruction,
+            quirks,
+            variant,
+            state,
+            step,
+            base,
+            data,
+            irq_sources,
+            nmi_line: lines & NMI_LINE != 0,
+            reset_line: lines & RESET_LINE != 0,
+            reset_pending: lines & RESET_PENDING != 0,
+            nmi_edge: lines & NMI_EDGE != 0,
+            poll: lines & POLL != 0,
+            prev_poll: lines & PREV_POLL != 0,
+            interrupt,
+        };
+        state.decoded()?;
+        Ok(state)
+    }
+}
+
+fn flag(set: bool, bit: u8) -> u8 {
+    if set {
+        bit
+    } else {
+        0
+    }
+}
+
+struct Writer<'a> {
+    buf: &'a mut [u8],
+    pos: usize,
+}
+
+impl Writer<'_> {
+    fn bytes(&mut self, b: &[u8]) {
+        self.buf[self.pos..self.pos + b.len()].copy_from_slice(b);
+        self.pos += b.len();
+    }
+    fn u8(&mut self, v: u8) {
+        self.bytes(&[v]);
+    }
+    fn u16(&mut self, v: u16) {
+        self.bytes(&v.to_le_bytes());
+    }
+}
+
+// Only used once the length has been checked against `CpuState::SIZE`.
+struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> [u8; N] {
+        let mut out = [0; N];
+        out.copy_from_slice(&self.buf[self.pos..self.pos + N]);
+        self.pos += N;
+        out
+    }
+    fn u8(&mut self) -> u8 {
+        self.take::<1>()[0]
+    }
+    fn u16(&mut self) -> u16 {
+        u16::from_le_bytes(self.take())
+    }
+    fn u32(&mut self) -> u32 {
+        u32::from_le_bytes(self.take())
+    }
+    fn u64(&mut self) -> u64 {
+        u64::from_le_bytes(self.take())
+    }
+}
+
+impl Cpu {
+    /// Captures the complete CPU state, mid-instruction or not.
+    pub fn save_state(&self) -> CpuState {
+        CpuState {
+            a: self.a,
+            x: self.x,
+            y: self.y,
+            p: self.s.get(),
+            sp: self.sp,
+            pc: self.pc,
+            addr: self.addr,
+            cycles: self.cycles,
+            instruction: self.instruction.get(),
+            quirks: self.quirks,
+            variant: self.variant,
+            state: self.state,
+            step: self.step,
+            base: self.base,
+            data: self.data,
+            irq_sources: self.irq_sources,
+            nmi_line: self.nmi_line,
+            reset_line: self.reset_line,
+            reset_pending: self.reset_pending,
+            nmi_edge: self.nmi_edge,
+            poll: self.poll,
+            prev_poll: self.prev_poll,
+            interrupt: self.interrupt,
+        }
+    }
+    /// Restores a state taken by `save_state`. The CPU then runs the same
+    /// bus cycles the saved one would have. A deserialized state no CPU can
+    /// be in is rejected and leaves the CPU untouched.
+    pub fn load_state(&mut self, state: &CpuState) -> Result<(), StateError> {
+        let (mode, op) = state.decoded()?;
+        self.a = state.a;
+        self.x = state.x;
+        self.y = state.y;
+        self.s.set(state.p);
+        self.sp = state.sp;
+        self.pc = state.pc;
+        self.addr = state.addr;
+        self.cycles = state.cycles;
+        self.instruction = Instruction(state.instruction);
+        self.quirks = state.quirks;
+        self.variant = state.variant;
+        self.state = state.state;
+        self.step = state.step;
+        self.base = state.base;
+        self.data = state.data;
+        self.irq_sources = state.irq_sources;
+        self.nmi_line = state.nmi_line;
+        self.reset_line = state.reset_line;
+        self.reset_pending = state.reset_pending;
+        self.nmi_edge = state.nmi_edge;
+        self.poll = state.poll;
+        self.prev_poll = state.prev_poll;
+        self.interrupt = state.interrupt;
+        self.mode = mode;
+        self.current_instr = op;
+        Ok(())
+    }
+}
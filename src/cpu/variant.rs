@@ -0,0 +1,31 @@
+/// CPU model being emulated.
+#[derive(Clone, Copy, PartialEq, Eq, Ord, PartialOrd, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Variant {
+    /// MOS 6502 as found in the Apple II and, as the 6510, the C64.
+    Nmos6502,
+    /// NES CPU: an NMOS core with the decimal adder disconnected, so D is
+    /// just a flag.
+    Ricoh2A03,
+    /// CMOS 65C02 with the WDC/Rockwell additions to the instruction set
+    /// (BRA, STZ, TRB/TSB, `(zp)`, ...). Every undefined opcode is a NOP.
+    Cmos65C02,
+    /// WDC W65C02S: the 65C02 plus the Rockwell RMB/SMB/BBR/BBS bit
+    /// instructions and WAI/STP.
+    W65C02S,
+}
+
+impl Variant {
+    pub fn has_decimal_mode(self) -> bool {
+        match self {
+            Variant::Nmos6502 | Variant::Cmos65C02 | Variant::W65C02S => true,
+            Variant::Ricoh2A03 => false,
+        }
+    }
+    pub fn is_cmos(self) -> bool {
+        matches!(self, Variant::Cmos65C02 | Variant::W65C02S)
+    }
+    pub fn has_bit_instructions(self) -> bool {
+        matches!(self, Variant::W65C02S)
+    }
+}
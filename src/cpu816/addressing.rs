@@ -0,0 +1,210 @@
+use super::{Bus24, Cpu816};
+
+/// Operand addressing of the data instructions.
+#[derive(PartialEq, Clone, Copy, Eq, Debug)]
+pub(super) enum Mode {
+    Accumulator,
+    Immediate,
+    Direct,
+    DirectX,
+    DirectY,
+    DirectIndirect,
+    DirectIndirectX,
+    DirectIndirectY,
+    DirectIndirectLong,
+    DirectIndirectLongY,
+    Absolute,
+    AbsoluteX,
+    AbsoluteY,
+    Long,
+    LongX,
+    StackRelative,
+    StackRelativeIndirectY,
+}
+
+// Bus access and effective address calculation. Every cycle of the 65C816
+// is either a bus access or an internal operation, so counting both gives
+// the documented cycle counts, including the penalties for 16-bit data, a
+// misaligned direct page and index carries.
+impl Cpu816 {
+    pub(super) fn read<B: Bus24 + ?Sized>(&mut self, bus: &mut B, addr: u32) -> u8 {
+        self.cycles += 1;
+        bus.read(addr & 0xFF_FFFF)
+    }
+    pub(super) fn write<B: Bus24 + ?Sized>(&mut self, bus: &mut B, addr: u32, val: u8) {
+        self.cycles += 1;
+        bus.write(addr & 0xFF_FFFF, val);
+    }
+    // Internal operation: VDA and VPA are both low, so nothing is accessed.
+    pub(super) fn io(&mut self) {
+        self.cycles += 1;
+    }
+    pub(super) fn read_word<B: Bus24 + ?Sized>(&mut self, bus: &mut B, addr: u32, wide: bool) -> u16 {
+        let lo = self.read(bus, addr) as u16;
+        if !wide {
+            return lo;
+        }
+        lo | (self.read(bus, addr.wrapping_add(1)) as u16) << 8
+    }
+    pub(super) fn write_word<B: Bus24 + ?Sized>(&mut self, bus: &mut B, addr: u32, val: u16, wide: bool) {
+        self.write(bus, addr, val as u8);
+        if wide {
+            self.write(bus, addr.wrapping_add(1), (val >> 8) as u8);
+        }
+    }
+    // Read-modify-write stores the high byte first.
+    pub(super) fn write_word_rev<B: Bus24 + ?Sized>(&mut self, bus: &mut B, addr: u32, val: u16, wide: bool) {
+        if wide {
+            self.write(bus, addr.wrapping_add(1), (val >> 8) as u8);
+        }
+        self.write(bus, addr, val as u8);
+    }
+    pub(super) fn fetch<B: Bus24 + ?Sized>(&mut self, bus: &mut B) -> u8 {
+        let val = self.read(bus, self.program_addr(self.pc));
+        self.pc = self.pc.wrapping_add(1);
+        val
+    }
+    pub(super) fn fetch16<B: Bus24 + ?Sized>(&mut self, bus: &mut B) -> u16 {
+        let lo = self.fetch(bus) as u16;
+        lo | (self.fetch(bus) as u16) << 8
+    }
+    pub(super) fn fetch24<B: Bus24 + ?Sized>(&mut self, bus: &mut B) -> u32 {
+        let lo = self.fetch16(bus) as u32;
+        lo | (self.fetch(bus) as u32) << 16
+    }
+    pub(super) fn program_addr(&self, addr: u16) -> u32 {
+        (self.pbr as u32) << 16 | addr as u32
+    }
+    pub(super) fn data_addr(&self, addr: u16) -> u32 {
+        (self.dbr as u32) << 16 | addr as u32
+    }
+    // The stack lives in page 1 in emulation mode and anywhere in bank 0
+    // in native mode.
+    pub(super) fn push<B: Bus24 + ?Sized>(&mut self, bus: &mut B, val: u8) {
+        self.write(bus, self.sp as u32, val);
+        self.sp = self.stack_wrap(self.sp.wrapping_sub(1));
+    }
+    pub(super) fn pull<B: Bus24 + ?Sized>(&mut self, bus: &mut B) -> u8 {
+        self.sp = self.stack_wrap(self.sp.wrapping_add(1));
+        self.read(bus, self.sp as u32)
+    }
+    pub(super) fn push16<B: Bus24 + ?Sized>(&mut self, bus: &mut B, val: u16) {
+        self.push(bus, (val >> 8) as u8);
+        self.push(bus, val as u8);
+    }
+    pub(super) fn pull16<B: Bus24 + ?Sized>(&mut self, bus: &mut B) -> u16 {
+        let lo = self.pull(bus) as u16;
+        lo | (self.pull(bus) as u16) << 8
+    }
+    pub(super) fn stack_wrap(&self, sp: u16) -> u16 {
+        if self.e {
+            0x0100 | (sp & 0xFF)
+        } else {
+            sp
+        }
+    }
+    // Direct page address of `offset + index`. With E set and the direct
+    // page aligned, indexing wraps within the page as on the 6502.
+    fn direct(&self, offset: u8, index: u16) -> u32 {
+        if self.e && self.d & 0xFF == 0 {
+            (self.d | ((offset as u16).wrapping_add(index) & 0xFF)) as u32
+        } else {
+            self.d.wrapping_add(offset as u16).wrapping_add(index) as u32
+        }
+    }
+    fn direct_offset<B: Bus24 + ?Sized>(&mut self, bus: &mut B) -> u8 {
+        let offset = self.fetch(bus);
+        if self.d & 0xFF != 0 {
+            self.io();
+        }
+        offset
+    }
+    fn direct_pointer<B: Bus24 + ?Sized>(&mut self, bus: &mut B, offset: u8, index: u16) -> u16 {
+        let lo = self.read(bus, self.direct(offset, index)) as u16;
+        lo | (self.read(bus, self.direct(offset, index.wrapping_add(1))) as u16) << 8
+    }
+    // Reads only pay for the index when it carries into the next page or
+    // the index registers are 16 bits wide.
+    fn indexed(&mut self, base: u32, index: u16, read: bool) -> u32 {
+        let addr = base.wrapping_add(index as u32) & 0xFF_FFFF;
+        if !read || self.x16() || (base ^ addr) & 0xFF00 != 0 {
+            self.io();
+        }
+        addr
+    }
+    /// Fetches the operand bytes of `mode` and returns the effective
+    /// address. `wide` sizes immediate operands; `read` is false for
+    /// stores and read-modify-writes, which always pay for indexing.
+    pub(super) fn effective<B: Bus24 + ?Sized>(&mut self, bus: &mut B, mode: Mode, wide: bool, read: bool) -> u32 {
+        match mode {
+            Mode::Accumulator => 0,
+            Mode::Immediate => {
+                let addr = self.program_addr(self.pc);
+                self.pc = self.pc.wrapping_add(1 + wide as u16);
+                addr
+            }
+            Mode::Direct => {
+                let offset = self.direct_offset(bus);
+                self.direct(offset, 0)
+            }
+            Mode::DirectX | Mode::DirectY => {
+                let offset = self.direct_offset(bus);
+                self.io();
+                let index = if mode == Mode::DirectX { self.x } else { self.y };
+                self.direct(offset, index)
+            }
+            Mode::DirectIndirect => {
+                let offset = self.direct_offset(bus);
+                let ptr = self.direct_pointer(bus, offset, 0);
+                self.data_addr(ptr)
+            }
+            Mode::DirectIndirectX => {
+                let offset = self.direct_offset(bus);
+                self.io();
+                let ptr = self.direct_pointer(bus, offset, self.x);
+                self.data_addr(ptr)
+            }
+            Mode::DirectIndirectY => {
+                let offset = self.direct_offset(bus);
+                let ptr = self.direct_pointer(bus, offset, 0);
+                self.indexed(self.data_addr(ptr), self.y, read)
+            }
+            Mode::DirectIndirectLong | Mode::DirectIndirectLongY => {
+                let offset = self.direct_offset(bus);
+                let ptr = self.direct_pointer(bus, offset, 0) as u32;
+                let bank = self.read(bus, self.direct(offset, 2)) as u32;
+                let addr = bank << 16 | ptr;
+                if mode == Mode::DirectIndirectLong {
+                    addr
+                } else {
+                    addr.wrapping_add(self.y as u32) & 0xFF_FFFF
+                }
+            }
+            Mode::Absolute => {
+                let addr = self.fetch16(bus);
+                self.data_addr(addr)
+            }
+            Mode::AbsoluteX | Mode::AbsoluteY => {
+                let addr = self.fetch16(bus);
+                let index = if mode == Mode::AbsoluteX { self.x } else { self.y };
+                self.indexed(self.data_addr(addr), index, read)
+            }
+            Mode::Long => self.fetch24(bus),
+            Mode::LongX => self.fetch24(bus).wrapping_add(self.x as u32) & 0xFF_FFFF,
+            Mode::StackRelative => {
+                let offset = self.fetch(bus);
+                self.io();
+                self.sp.wrapping_add(offset as u16) as u32
+            }
+            Mode::StackRelativeIndirectY => {
+                let offset = self.fetch(bus);
+                self.io();
+                let ptr = self.sp.wrapping_add(offset as u16) as u32;
+                let lo = self.read(bus, ptr) as u16;
+                let hi = self.read(bus, ptr.wrapping_add(1) & 0xFFFF) as u16;
+                self.io();
+                self.data_addr(lo | hi << 8).wrapping_add(self.y as u32) & 0xFF_FFFF
+            }
+        }
+    }
+}
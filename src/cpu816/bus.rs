@@ -0,0 +1,20 @@
+use core::ops::IndexMut;
+
+/// 24-bit address space seen by the 65C816: the bank byte in bits 16-23,
+/// then the 16-bit address within the bank.
+///
+/// Like `Bus`, reads take `&mut self` so devices can react to them.
+pub trait Bus24 {
+    fn read(&mut self, addr: u32) -> u8;
+    fn write(&mut self, addr: u32, val: u8);
+}
+
+/// Plain memories indexed by `u32` have no read side effects.
+impl<T: IndexMut<u32, Output = u8> + ?Sized> Bus24 for T {
+    fn read(&mut self, addr: u32) -> u8 {
+        self[addr]
+    }
+    fn write(&mut self, addr: u32, val: u8) {
+        self[addr] = val;
+    }
+}
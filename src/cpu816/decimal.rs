@@ -0,0 +1,51 @@
+use super::Cpu816;
+
+// ADC and SBC at either width. In decimal mode each nibble is corrected as
+// it is added, so invalid BCD digits behave as on the real chip; V comes
+// from the sum before the top digit is corrected. Unlike the NMOS 6502,
+// N and Z always reflect the result.
+impl Cpu816 {
+    pub(super) fn add(&mut self, m: u16, subtract: bool) {
+        let wide = self.m16();
+        let mask = self.mask(wide) as i32;
+        let sign = if wide { 0x8000 } else { 0x80 };
+        let a = self.a as i32 & mask;
+        let m = if subtract { !m as i32 & mask } else { m as i32 & mask };
+        let mut carry = self.s.get_carry() as i32;
+        let mut res;
+        if self.s.get_decimal() {
+            let digits = if wide { 4 } else { 2 };
+            res = 0;
+            for digit in 0..digits {
+                let shift = 4 * digit;
+                let nibble = 0xF << shift;
+                res = (a & nibble) + (m & nibble) + (carry << shift) + (res & ((1 << shift) - 1));
+                if digit == digits - 1 {
+                    break;
+                }
+                let limit = (0x10 << shift) - 1;
+                if !subtract && res > (0x0A << shift) - 1 {
+                    res += 0x06 << shift;
+                } else if subtract && res <= limit {
+                    res -= 0x06 << shift;
+                }
+                carry = (res > limit) as i32;
+            }
+        } else {
+            res = a + m + carry;
+        }
+        self.s.set_overflow(!(a ^ m) & (a ^ res) & sign != 0);
+        if self.s.get_decimal() {
+            let top = 4 * (if wide { 3 } else { 1 });
+            if !subtract && res > (0x0A << top) - 1 {
+                res += 0x06 << top;
+            } else if subtract && res <= mask {
+                res -= 0x06 << top;
+            }
+        }
+        self.s.set_carry(res > mask);
+        let res = (res & mask) as u16;
+        self.a = self.merge_a(res);
+        self.set_flags_z_n(res, wide);
+    }
+}
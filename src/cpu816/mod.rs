@@ -0,0 +1,725 @@
+#![allow(non_snake_case)]
+
+mod addressing;
+mod bus;
+mod decimal;
+pub use self::bus::Bus24;
+use self::addressing::Mode;
+use crate::cpu::flags::Flags;
+use crate::cpu::instruction::Instruction;
+use crate::{CpuError, RunState, StepOutcome};
+
+/// What a data instruction does with its operand.
+#[derive(Clone, Copy, Debug)]
+enum Op {
+    Read(fn(&mut Cpu816, u16)),
+    Write(fn(&mut Cpu816) -> u16),
+    Modify(fn(&mut Cpu816, u16) -> u16),
+}
+
+/// Which flag sizes an operand: M for the accumulator and memory, X for
+/// the index registers.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Width {
+    M,
+    X,
+}
+
+/// 65C816 core. It comes out of reset in 6502 emulation mode and switches
+/// to native mode with `CLC; XCE`.
+///
+/// Unlike `Cpu`, which runs one bus cycle per `tick`, this core executes
+/// whole instructions; `cycles` still counts every bus cycle.
+#[derive(Clone, Debug)]
+pub struct Cpu816 {
+    /// The 16-bit accumulator C. With M set, A is its low byte and the
+    /// high byte B is only reachable through XBA and the transfers.
+    pub a: u16,
+    pub x: u16,
+    pub y: u16,
+    pub s: Flags,
+    pub sp: u16,
+    pub pc: u16,
+    /// Program bank.
+    pub pbr: u8,
+    /// Data bank.
+    pub dbr: u8,
+    /// Direct page.
+    pub d: u16,
+    /// Emulation mode.
+    pub e: bool,
+    /// Cycles run since the CPU was created. Never reset.
+    pub cycles: u64,
+    pub instruction: Instruction,
+    pub state: RunState,
+}
+impl PartialEq for Cpu816 {
+    fn eq(&self, other: &Cpu816) -> bool {
+        self.a == other.a
+            && self.x == other.x
+            && self.y == other.y
+            && self.s == other.s
+            && self.sp == other.sp
+            && self.pc == other.pc
+            && self.pbr == other.pbr
+            && self.dbr == other.dbr
+            && self.d == other.d
+            && self.e == other.e
+    }
+}
+impl Eq for Cpu816 {}
+
+const P_INDEX_8BIT: u8 = 0x10;
+const P_MEMORY_8BIT: u8 = 0x20;
+
+impl Cpu816 {
+    pub fn new(init_pc: Option<u16>) -> Cpu816 {
+        let mut s = Flags::new();
+        s.set(P_MEMORY_8BIT | P_INDEX_8BIT | 0x04);
+        Cpu816 {
+            a: 0,
+            x: 0,
+            y: 0,
+            s,
+            sp: 0x01FD,
+            pc: init_pc.unwrap_or(0),
+            pbr: 0,
+            dbr: 0,
+            d: 0,
+            e: true,
+            cycles: 0,
+            instruction: Instruction(0xEA),
+            state: RunState::Running,
+        }
+    }
+    pub fn m16(&self) -> bool {
+        self.s.get() & P_MEMORY_8BIT == 0
+    }
+    pub fn x16(&self) -> bool {
+        self.s.get() & P_INDEX_8BIT == 0
+    }
+    fn wide(&self, width: Width) -> bool {
+        match width {
+            Width::M => self.m16(),
+            Width::X => self.x16(),
+        }
+    }
+    // Re-establishes what the current E, M and X imply after anything that
+    // can change them: emulation mode forces 8-bit registers and a page 1
+    // stack, and 8-bit index registers lose their high bytes.
+    fn update_mode(&mut self) {
+        if self.e {
+            self.s.set(self.s.get() | P_MEMORY_8BIT | P_INDEX_8BIT);
+            self.sp = 0x0100 | (self.sp & 0xFF);
+        }
+        if !self.x16() {
+            self.x &= 0xFF;
+            self.y &= 0xFF;
+        }
+    }
+    /// Loads PC from the reset vector in emulation mode.
+    pub fn start<B: Bus24 + ?Sized>(&mut self, bus: &mut B) {
+        self.state = RunState::Running;
+        self.e = true;
+        self.d = 0;
+        self.pbr = 0;
+        self.dbr = 0;
+        self.s.set_interrupt(true);
+        self.s.set_decimal(false);
+        self.update_mode();
+        self.cycles += 5;
+        let lo = self.read(bus, 0xFFFC) as u16;
+        self.pc = lo | (self.read(bus, 0xFFFD) as u16) << 8;
+    }
+    pub fn irq<B: Bus24 + ?Sized>(&mut self, bus: &mut B) {
+        if self.state == RunState::Waiting {
+            self.state = RunState::Running;
+        }
+        if !self.s.get_interrupt() && self.state == RunState::Running {
+            self.interrupt(bus, 0xFFEE, 0xFFFE, false);
+        }
+    }
+    pub fn nmi<B: Bus24 + ?Sized>(&mut self, bus: &mut B) {
+        if self.state == RunState::Waiting {
+            self.state = RunState::Running;
+        }
+        if self.state == RunState::Running {
+            self.interrupt(bus, 0xFFEA, 0xFFFA, false);
+        }
+    }
+    // Shared by BRK, COP, IRQ and NMI. Native mode also saves the program
+    // bank; emulation mode tells BRK from IRQ by bit 4 of the pushed P.
+    fn interrupt<B: Bus24 + ?Sized>(&mut self, bus: &mut B, native: u16, emulation: u16, software: bool) {
+        if !software {
+            self.io();
+            self.io();
+        }
+        if !self.e {
+            self.push(bus, self.pbr);
+        }
+        self.push16(bus, self.pc);
+        let p = if self.e && !software { self.s.get() & !P_INDEX_8BIT } else { self.s.get() };
+        self.push(bus, p);
+        self.s.set_interrupt(true);
+        self.s.set_decimal(false);
+        self.pbr = 0;
+        let vector = if self.e { emulation } else { native } as u32;
+        let lo = self.read(bus, vector) as u16;
+        self.pc = lo | (self.read(bus, vector + 1) as u16) << 8;
+    }
+    /// Runs one instruction. A waiting or stopped CPU only burns a single
+    /// idle cycle, as `Cpu::run_instr` does, and a stopped one reports the
+    /// STP that halted it.
+    pub fn run_instr<B: Bus24 + ?Sized>(&mut self, bus: &mut B) -> Result<StepOutcome, CpuError> {
+        let start = self.cycles;
+        if self.state == RunState::Running {
+            let opcode = self.fetch(bus);
+            self.instruction.set(opcode);
+            self.execute(bus);
+        } else {
+            self.io();
+        }
+        match self.state {
+            RunState::Stopped => Err(CpuError::Halted {
+                pc: self.pc.wrapping_sub(1),
+                opcode: self.instruction.get(),
+            }),
+            state => Ok(StepOutcome {
+                cycles: self.cycles - start,
+                state,
+            }),
+        }
+    }
+    fn execute<B: Bus24 + ?Sized>(&mut self, bus: &mut B) {
+        use Mode::*;
+        match self.instruction.get() {
+            0x00 => {
+                self.fetch(bus);
+                self.interrupt(bus, 0xFFE6, 0xFFFE, true);
+            }
+            0x02 => {
+                self.fetch(bus);
+                self.interrupt(bus, 0xFFE4, 0xFFF4, true);
+            }
+            0x04 => self.data(bus, Direct, Op::Modify(Cpu816::TSB), Width::M),
+            0x0C => self.data(bus, Absolute, Op::Modify(Cpu816::TSB), Width::M),
+            0x14 => self.data(bus, Direct, Op::Modify(Cpu816::TRB), Width::M),
+            0x1C => self.data(bus, Absolute, Op::Modify(Cpu816::TRB), Width::M),
+            0x24 => self.data(bus, Direct, Op::Read(Cpu816::BIT), Width::M),
+            0x2C => self.data(bus, Absolute, Op::Read(Cpu816::BIT), Width::M),
+            0x34 => self.data(bus, DirectX, Op::Read(Cpu816::BIT), Width::M),
+            0x3C => self.data(bus, AbsoluteX, Op::Read(Cpu816::BIT), Width::M),
+            0x89 => self.data(bus, Immediate, Op::Read(Cpu816::BIT_IMM), Width::M),
+            0x64 => self.data(bus, Direct, Op::Write(Cpu816::STZ), Width::M),
+            0x74 => self.data(bus, DirectX, Op::Write(Cpu816::STZ), Width::M),
+            0x9C => self.data(bus, Absolute, Op::Write(Cpu816::STZ), Width::M),
+            0x9E => self.data(bus, AbsoluteX, Op::Write(Cpu816::STZ), Width::M),
+            0x84 => self.data(bus, Direct, Op::Write(Cpu816::STY), Width::X),
+            0x8C => self.data(bus, Absolute, Op::Write(Cpu816::STY), Width::X),
+            0x94 => self.data(bus, DirectX, Op::Write(Cpu816::STY), Width::X),
+            0xA0 => self.data(bus, Immediate, Op::Read(Cpu816::LDY), Width::X),
+            0xA4 => self.data(bus, Direct, Op::Read(Cpu816::LDY), Width::X),
+            0xAC => self.data(bus, Absolute, Op::Read(Cpu816::LDY), Width::X),
+            0xB4 => self.data(bus, DirectX, Op::Read(Cpu816::LDY), Width::X),
+            0xBC => self.data(bus, AbsoluteX, Op::Read(Cpu816::LDY), Width::X),
+            0xC0 => self.data(bus, Immediate, Op::Read(Cpu816::CPY), Width::X),
+            0xC4 => self.data(bus, Direct, Op::Read(Cpu816::CPY), Width::X),
+            0xCC => self.data(bus, Absolute, Op::Read(Cpu816::CPY), Width::X),
+            0xE0 => self.data(bus, Immediate, Op::Read(Cpu816::CPX), Width::X),
+            0xE4 => self.data(bus, Direct, Op::Read(Cpu816::CPX), Width::X),
+            0xEC => self.data(bus, Absolute, Op::Read(Cpu816::CPX), Width::X),
+            0x86 => self.data(bus, Direct, Op::Write(Cpu816::STX), Width::X),
+            0x8E => self.data(bus, Absolute, Op::Write(Cpu816::STX), Width::X),
+            0x96 => self.data(bus, DirectY, Op::Write(Cpu816::STX), Width::X),
+            0xA2 => self.data(bus, Immediate, Op::Read(Cpu816::LDX), Width::X),
+            0xA6 => self.data(bus, Direct, Op::Read(Cpu816::LDX), Width::X),
+            0xAE => self.data(bus, Absolute, Op::Read(Cpu816::LDX), Width::X),
+            0xB6 => self.data(bus, DirectY, Op::Read(Cpu816::LDX), Width::X),
+            0xBE => self.data(bus, AbsoluteY, Op::Read(Cpu816::LDX), Width::X),
+            0x1A => self.data(bus, Accumulator, Op::Modify(Cpu816::INC), Width::M),
+            0x3A => self.data(bus, Accumulator, Op::Modify(Cpu816::DEC), Width::M),
+
+            // branches and jumps
+            0x10 | 0x30 | 0x50 | 0x70 | 0x90 | 0xB0 | 0xD0 | 0xF0 | 0x80 => {
+                let offset = self.fetch(bus) as i8 as u16;
+                if self.branch_taken() {
+                    self.io();
+                    let target = self.pc.wrapping_add(offset);
+                    if self.e && (target ^ self.pc) & 0xFF00 != 0 {
+                        self.io();
+                    }
+                    self.pc = target;
+                }
+            }
+            0x82 => {
+                let offset = self.fetch16(bus);
+                self.io();
+                self.pc = self.pc.wrapping_add(offset);
+            }
+            0x4C => self.pc = self.fetch16(bus),
+            0x5C => {
+                let target = self.fetch24(bus);
+                self.pc = target as u16;
+                self.pbr = (target >> 16) as u8;
+            }
+            0x6C => {
+                let ptr = self.fetch16(bus) as u32;
+                self.pc = self.read_word(bus, ptr, true);
+            }
+            0x7C => {
+                let ptr = self.fetch16(bus).wrapping_add(self.x);
+                self.io();
+                let lo = self.read(bus, self.program_addr(ptr)) as u16;
+                self.pc = lo | (self.read(bus, self.program_addr(ptr.wrapping_add(1))) as u16) << 8;
+            }
+            0xDC => {
+                let ptr = self.fetch16(bus) as u32;
+                self.pc = self.read_word(bus, ptr, true);
+                self.pbr = self.read(bus, (ptr + 2) & 0xFFFF);
+            }
+            0x20 => {
+                let target = self.fetch16(bus);
+                self.io();
+                self.push16(bus, self.pc.wrapping_sub(1));
+                self.pc = target;
+            }
+            0x22 => {
+                let target = self.fetch16(bus);
+                self.push(bus, self.pbr);
+                self.io();
+                let bank = self.fetch(bus);
+                self.push16(bus, self.pc.wrapping_sub(1));
+                self.pc = target;
+                self.pbr = bank;
+            }
+            0xFC => {
+                let lo = self.fetch(bus) as u16;
+                self.push16(bus, self.pc);
+                let ptr = (lo | (self.fetch(bus) as u16) << 8).wrapping_add(self.x);
+                self.io();
+                let lo = self.read(bus, self.program_addr(ptr)) as u16;
+                self.pc = lo | (self.read(bus, self.program_addr(ptr.wrapping_add(1))) as u16) << 8;
+            }
+            0x60 => {
+                self.io();
+                self.io();
+                self.pc = self.pull16(bus).wrapping_add(1);
+                self.io();
+            }
+            0x6B => {
+                self.io();
+                self.io();
+                self.pc = self.pull16(bus).wrapping_add(1);
+                self.pbr = self.pull(bus);
+            }
+            0x40 => {
+                self.io();
+                self.io();
+                let p = self.pull(bus);
+                self.s.set(p);
+                self.update_mode();
+                self.pc = self.pull16(bus);
+                if !self.e {
+                    self.pbr = self.pull(bus);
+                }
+            }
+
+            // stack
+            0x08 => {
+                self.io();
+                self.push(bus, self.s.get());
+            }
+            0x28 => {
+                self.io();
+                self.io();
+                let p = self.pull(bus);
+                self.s.set(p);
+                self.update_mode();
+            }
+            0x48 => self.push_register(bus, self.a, self.m16()),
+            0xDA => self.push_register(bus, self.x, self.x16()),
+            0x5A => self.push_register(bus, self.y, self.x16()),
+            0x68 => {
+                let val = self.pull_register(bus, self.m16());
+                self.a = self.merge_a(val);
+            }
+            0xFA => self.x = self.pull_register(bus, self.x16()),
+            0x7A => self.y = self.pull_register(bus, self.x16()),
+            0x0B => self.push_register(bus, self.d, true),
+            0x2B => self.d = self.pull_register(bus, true),
+            0x8B => self.push_register(bus, self.dbr as u16, false),
+            0xAB => self.dbr = self.pull_register(bus, false) as u8,
+            0x4B => self.push_register(bus, self.pbr as u16, false),
+            0xF4 => {
+                let val = self.fetch16(bus);
+                self.push16(bus, val);
+            }
+            0xD4 => {
+                let ptr = self.effective(bus, Direct, true, true);
+                let val = self.read_word(bus, ptr, true);
+                self.push16(bus, val);
+            }
+            0x62 => {
+                let offset = self.fetch16(bus);
+                self.io();
+                self.push16(bus, self.pc.wrapping_add(offset));
+            }
+
+            // block moves, one byte per execution
+            0x44 | 0x54 => {
+                self.dbr = self.fetch(bus);
+                let src_bank = self.fetch(bus);
+                let val = self.read(bus, (src_bank as u32) << 16 | self.x as u32);
+                self.write(bus, self.data_addr(self.y), val);
+                self.io();
+                self.io();
+                let step = if self.instruction.get() == 0x54 { 1 } else { 0xFFFF };
+                let mask = if self.x16() { 0xFFFF } else { 0x00FF };
+                self.x = self.x.wrapping_add(step) & mask;
+                self.y = self.y.wrapping_add(step) & mask;
+                self.a = self.a.wrapping_sub(1);
+                if self.a != 0xFFFF {
+                    self.pc = self.pc.wrapping_sub(3);
+                }
+            }
+
+            // status and mode
+            0xC2 | 0xE2 => {
+                let mask = self.fetch(bus);
+                self.io();
+                let p = if self.instruction.get() == 0xC2 {
+                    self.s.get() & !mask
+                } else {
+                    self.s.get() | mask
+                };
+                self.s.set(p);
+                self.update_mode();
+            }
+            0xFB => {
+                self.io();
+                let carry = self.s.get_carry();
+                self.s.set_carry(self.e);
+                self.e = carry;
+                self.update_mode();
+            }
+            0x42 => {
+                self.fetch(bus);
+            }
+            0xCB => {
+                self.io();
+                self.io();
+                self.state = RunState::Waiting;
+            }
+            0xDB => {
+                self.io();
+                self.io();
+                self.state = RunState::Stopped;
+            }
+            0xEB => {
+                self.io();
+                self.io();
+                self.a = self.a.rotate_left(8);
+                self.set_flags_z_n(self.a, false);
+            }
+            _ if self.instruction.cc() == 1 => {
+                let mode = match self.instruction.bbb() {
+                    0 => DirectIndirectX,
+                    1 => Direct,
+                    2 => Immediate,
+                    3 => Absolute,
+                    4 => DirectIndirectY,
+                    5 => DirectX,
+                    6 => AbsoluteY,
+                    _ => AbsoluteX,
+                };
+                self.data(bus, mode, self.alu_op(), Width::M)
+            }
+            _ if self.instruction.cc() == 3 && !matches!(self.instruction.bbb(), 2 | 6) => {
+                let mode = match self.instruction.bbb() {
+                    0 => StackRelative,
+                    1 => DirectIndirectLong,
+                    3 => Long,
+                    4 => StackRelativeIndirectY,
+                    5 => DirectIndirectLongY,
+                    _ => LongX,
+                };
+                self.data(bus, mode, self.alu_op(), Width::M)
+            }
+            _ if self.instruction.get() & 0x1F == 0x12 => {
+                self.data(bus, DirectIndirect, self.alu_op(), Width::M)
+            }
+            _ if self.instruction.cc() == 2 && self.instruction.bbb() & 0x01 == 1 => {
+                let op = match self.instruction.aaa() {
+                    0 => Cpu816::ASL,
+                    1 => Cpu816::ROL,
+                    2 => Cpu816::LSR,
+                    3 => Cpu816::ROR,
+                    6 => Cpu816::DEC,
+                    _ => Cpu816::INC,
+                };
+                let mode = match self.instruction.bbb() {
+                    1 => Direct,
+                    3 => Absolute,
+                    5 => DirectX,
+                    _ => AbsoluteX,
+                };
+                self.data(bus, mode, Op::Modify(op), Width::M)
+            }
+            0x0A => self.data(bus, Accumulator, Op::Modify(Cpu816::ASL), Width::M),
+            0x2A => self.data(bus, Accumulator, Op::Modify(Cpu816::ROL), Width::M),
+            0x4A => self.data(bus, Accumulator, Op::Modify(Cpu816::LSR), Width::M),
+            0x6A => self.data(bus, Accumulator, Op::Modify(Cpu816::ROR), Width::M),
+            _ => {
+                self.io();
+                self.implied();
+            }
+        }
+    }
+    fn alu_op(&self) -> Op {
+        match self.instruction.aaa() {
+            0 => Op::Read(Cpu816::ORA),
+            1 => Op::Read(Cpu816::AND),
+            2 => Op::Read(Cpu816::EOR),
+            3 => Op::Read(Cpu816::ADC),
+            4 => Op::Write(Cpu816::STA),
+            5 => Op::Read(Cpu816::LDA),
+            6 => Op::Read(Cpu816::CMP),
+            _ => Op::Read(Cpu816::SBC),
+        }
+    }
+    // Data instruction: address the operand, then read, write or modify
+    // it at the width selected by M or X.
+    fn data<B: Bus24 + ?Sized>(&mut self, bus: &mut B, mode: Mode, op: Op, width: Width) {
+        let wide = self.wide(width);
+        let read = matches!(op, Op::Read(_));
+        let addr = self.effective(bus, mode, wide, read);
+        match op {
+            Op::Read(f) => {
+                let m = self.read_word(bus, addr, wide);
+                f(self, m);
+            }
+            Op::Write(f) => {
+                let m = f(self);
+                self.write_word(bus, addr, m, wide);
+            }
+            Op::Modify(f) if mode == Mode::Accumulator => {
+                self.io();
+                let m = f(self, self.a & self.mask(wide));
+                self.a = self.merge_a(m);
+            }
+            Op::Modify(f) => {
+                let m = self.read_word(bus, addr, wide);
+                // emulation mode writes the unmodified value back, as the
+                // 6502 does
+                if self.e {
+                    self.write(bus, addr, m as u8);
+                } else {
+                    self.io();
+                }
+                let res = f(self, m);
+                self.write_word_rev(bus, addr, res, wide);
+            }
+        }
+    }
+    fn push_register<B: Bus24 + ?Sized>(&mut self, bus: &mut B, val: u16, wide: bool) {
+        self.io();
+        if wide {
+            self.push(bus, (val >> 8) as u8);
+        }
+        self.push(bus, val as u8);
+    }
+    fn pull_register<B: Bus24 + ?Sized>(&mut self, bus: &mut B, wide: bool) -> u16 {
+        self.io();
+        self.io();
+        let val = if wide { self.pull16(bus) } else { self.pull(bus) as u16 };
+        self.set_flags_z_n(val, wide);
+        val
+    }
+    fn branch_taken(&self) -> bool {
+        if self.instruction.get() == 0x80 {
+            return true;
+        }
+        match self.instruction.xx() {
+            0 => self.s.get_negative() == self.instruction.y(),
+            1 => self.s.get_overflow() == self.instruction.y(),
+            2 => self.s.get_carry() == self.instruction.y(),
+            _ => self.s.get_zero() == self.instruction.y(),
+        }
+    }
+    // Single-byte register and flag instructions.
+    fn implied(&mut self) {
+        let m16 = self.m16();
+        let x16 = self.x16();
+        match self.instruction.get() {
+            0x18 => self.s.set_carry(false),
+            0x38 => self.s.set_carry(true),
+            0x58 => self.s.set_interrupt(false),
+            0x78 => self.s.set_interrupt(true),
+            0xB8 => self.s.set_overflow(false),
+            0xD8 => self.s.set_decimal(false),
+            0xF8 => self.s.set_decimal(true),
+            0xAA => self.x = self.transfer(self.a, x16),
+            0xA8 => self.y = self.transfer(self.a, x16),
+            0xBA => self.x = self.transfer(self.sp, x16),
+            0x9B => self.y = self.transfer(self.x, x16),
+            0xBB => self.x = self.transfer(self.y, x16),
+            0x8A => {
+                let val = self.transfer(self.x, m16);
+                self.a = self.merge_a(val);
+            }
+            0x98 => {
+                let val = self.transfer(self.y, m16);
+                self.a = self.merge_a(val);
+            }
+            0x9A => self.sp = self.stack_wrap(self.x),
+            0x1B => self.sp = self.stack_wrap(self.a),
+            0x3B => self.a = self.transfer(self.sp, true),
+            0x5B => self.d = self.transfer(self.a, true),
+            0x7B => self.a = self.transfer(self.d, true),
+            0xE8 => self.x = self.transfer(self.x.wrapping_add(1), x16),
+            0xC8 => self.y = self.transfer(self.y.wrapping_add(1), x16),
+            0xCA => self.x = self.transfer(self.x.wrapping_sub(1), x16),
+            0x88 => self.y = self.transfer(self.y.wrapping_sub(1), x16),
+            _ => {}
+        }
+    }
+    fn transfer(&mut self, val: u16, wide: bool) -> u16 {
+        let val = val & self.mask(wide);
+        self.set_flags_z_n(val, wide);
+        val
+    }
+    fn mask(&self, wide: bool) -> u16 {
+        if wide {
+            0xFFFF
+        } else {
+            0x00FF
+        }
+    }
+    // With M set only A changes; B keeps its value.
+    fn merge_a(&self, val: u16) -> u16 {
+        if self.m16() {
+            val
+        } else {
+            (self.a & 0xFF00) | (val & 0xFF)
+        }
+    }
+    fn set_flags_z_n(&mut self, val: u16, wide: bool) {
+        let sign = if wide { 0x8000 } else { 0x80 };
+        self.s.set_zero(val & self.mask(wide) == 0);
+        self.s.set_negative(val & sign != 0);
+    }
+    fn compare(&mut self, reg: u16, m: u16, wide: bool) {
+        let reg = reg & self.mask(wide);
+        self.s.set_carry(reg >= m);
+        self.set_flags_z_n(reg.wrapping_sub(m), wide);
+    }
+    fn ORA(&mut self, m: u16) {
+        self.a = self.merge_a(self.a | m);
+        self.set_flags_z_n(self.a, self.m16());
+    }
+    fn AND(&mut self, m: u16) {
+        self.a = self.merge_a(self.a & m);
+        self.set_flags_z_n(self.a, self.m16());
+    }
+    fn EOR(&mut self, m: u16) {
+        self.a = self.merge_a(self.a ^ m);
+        self.set_flags_z_n(self.a, self.m16());
+    }
+    fn ADC(&mut self, m: u16) {
+        self.add(m, false);
+    }
+    fn SBC(&mut self, m: u16) {
+        self.add(m, true);
+    }
+    fn STA(&mut self) -> u16 {
+        self.a
+    }
+    fn LDA(&mut self, m: u16) {
+        self.a = self.merge_a(m);
+        self.set_flags_z_n(m, self.m16());
+    }
+    fn CMP(&mut self, m: u16) {
+        self.compare(self.a, m, self.m16());
+    }
+    fn BIT(&mut self, m: u16) {
+        let wide = self.m16();
+        let sign = if wide { 0x8000 } else { 0x80 };
+        self.s.set_zero(self.a & m & self.mask(wide) == 0);
+        self.s.set_negative(m & sign != 0);
+        self.s.set_overflow(m & (sign >> 1) != 0);
+    }
+    fn BIT_IMM(&mut self, m: u16) {
+        self.s.set_zero(self.a & m & self.mask(self.m16()) == 0);
+    }
+    fn STZ(&mut self) -> u16 {
+        0
+    }
+    fn TSB(&mut self, m: u16) -> u16 {
+        self.s.set_zero(self.a & m & self.mask(self.m16()) == 0);
+        m | self.a
+    }
+    fn TRB(&mut self, m: u16) -> u16 {
+        self.s.set_zero(self.a & m & self.mask(self.m16()) == 0);
+        m & !self.a
+    }
+    fn ASL(&mut self, m: u16) -> u16 {
+        let wide = self.m16();
+        let sign = if wide { 0x8000 } else { 0x80 };
+        let res = (m << 1) & self.mask(wide);
+        self.s.set_carry(m & sign != 0);
+        self.set_flags_z_n(res, wide);
+        res
+    }
+    fn ROL(&mut self, m: u16) -> u16 {
+        let wide = self.m16();
+        let sign = if wide { 0x8000 } else { 0x80 };
+        let res = ((m << 1) | self.s.get_carry() as u16) & self.mask(wide);
+        self.s.set_carry(m & sign != 0);
+        self.set_flags_z_n(res, wide);
+        res
+    }
+    fn LSR(&mut self, m: u16) -> u16 {
+        let res = m >> 1;
+        self.s.set_carry(m & 1 == 1);
+        self.set_flags_z_n(res, self.m16());
+        res
+    }
+    fn ROR(&mut self, m: u16) -> u16 {
+        let wide = self.m16();
+        let sign = if wide { 0x8000 } else { 0x80 };
+        let res = (m >> 1) | if self.s.get_carry() { sign } else { 0 };
+        self.s.set_carry(m & 1 == 1);
+        self.set_flags_z_n(res, wide);
+        res
+    }
+    fn INC(&mut self, m: u16) -> u16 {
+        let res = m.wrapping_add(1) & self.mask(self.m16());
+        self.set_flags_z_n(res, self.m16());
+        res
+    }
+    fn DEC(&mut self, m: u16) -> u16 {
+        let res = m.wrapping_sub(1) & self.mask(self.m16());
+        self.set_flags_z_n(res, self.m16());
+        res
+    }
+    fn STX(&mut self) -> u16 {
+        self.x
+    }
+    fn STY(&mut self) -> u16 {
+        self.y
+    }
+    fn LDX(&mut self, m: u16) {
+        self.x = m;
+        self.set_flags_z_n(m, self.x16());
+    }
+    fn LDY(&mut self, m: u16) {
+        self.y = m;
+        self.set_flags_z_n(m, self.x16());
+    }
+    fn CPX(&mut self, m: u16) {
+        self.compare(self.x, m, self.x16());
+    }
+    fn CPY(&mut self, m: u16) {
+        self.compare(self.y, m, self.x16());
+    }
+}
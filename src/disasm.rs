@@ -0,0 +1,181 @@
+use crate::cpu::{opcode_info, AddrMode, Cpu, DebugBus, OpcodeInfo, Variant};
+use core::fmt;
+
+/// One decoded instruction. Decoding only peeks memory, so it never
+/// disturbs the bus or the CPU.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Disassembled {
+    pub addr: u16,
+    pub info: &'static OpcodeInfo,
+    /// Address the operand reads or writes. Indexed and indirect modes
+    /// only resolve when decoded with `decode_at`.
+    pub effective: Option<u16>,
+    /// Where a branch, jump or JSR goes; for a branch, whether or not it
+    /// is taken.
+    pub target: Option<u16>,
+    bytes: [u8; 3],
+}
+
+impl Disassembled {
+    /// The opcode and operand bytes.
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes[..self.info.len as usize]
+    }
+    pub fn mnemonic(&self) -> &'static str {
+        self.info.mnemonic
+    }
+    /// Whether the opcode is documented for the CPU it was decoded for.
+    pub fn is_official(&self) -> bool {
+        self.info.official
+    }
+    /// The operand in standard syntax, such as `#$10`, `$44,X` or
+    /// `($20),Y`. Branches show their target.
+    pub fn operand(&self) -> Operand<'_> {
+        Operand(self)
+    }
+    /// Address of the next instruction in memory.
+    pub fn next(&self) -> u16 {
+        self.addr.wrapping_add(self.info.len as u16)
+    }
+    fn zp(&self) -> u8 {
+        self.bytes[1]
+    }
+    fn abs(&self) -> u16 {
+        u16::from_le_bytes([self.bytes[1], self.bytes[2]])
+    }
+}
+
+impl fmt::Display for Disassembled {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.info.mode {
+            AddrMode::Implied => f.write_str(self.mnemonic()),
+            _ => write!(f, "{} {}", self.mnemonic(), self.operand()),
+        }
+    }
+}
+
+/// Formats the operand of a `Disassembled`.
+pub struct Operand<'a>(&'a Disassembled);
+
+impl fmt::Display for Operand<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let d = self.0;
+        match d.info.mode {
+            AddrMode::Implied => Ok(()),
+            AddrMode::Accumulator => f.write_str("A"),
+            AddrMode::Immediate => write!(f, "#${:02X}", d.zp()),
+            AddrMode::ZeroPage => write!(f, "${:02X}", d.zp()),
+            AddrMode::ZeroPageX => write!(f, "${:02X},X", d.zp()),
+            AddrMode::ZeroPageY => write!(f, "${:02X},Y", d.zp()),
+            AddrMode::Absolute => write!(f, "${:04X}", d.abs()),
+            AddrMode::AbsoluteX => write!(f, "${:04X},X", d.abs()),
+            AddrMode::AbsoluteY => write!(f, "${:04X},Y", d.abs()),
+            AddrMode::Indirect => write!(f, "(${:04X})", d.abs()),
+            AddrMode::IndirectX => write!(f, "(${:02X},X)", d.zp()),
+            AddrMode::IndirectY => write!(f, "(${:02X}),Y", d.zp()),
+            AddrMode::ZeroPageIndirect => write!(f, "(${:02X})", d.zp()),
+            AddrMode::AbsoluteIndirectX => write!(f, "(${:04X},X)", d.abs()),
+            AddrMode::Relative => write!(f, "${:04X}", d.target.unwrap_or(0)),
+            AddrMode::ZeroPageRelative => write!(f, "${:02X},${:04X}", d.zp(), d.target.unwrap_or(0)),
+        }
+    }
+}
+
+/// Decodes the instruction at the start of `bytes`, which sits at `addr`.
+/// Returns None if `bytes` ends inside it.
+pub fn decode_slice(bytes: &[u8], addr: u16, variant: Variant) -> Option<Disassembled> {
+    let info = opcode_info(variant, *bytes.first()?);
+    let operand = bytes.get(..info.len as usize)?;
+    let mut raw = [0; 3];
+    raw[..operand.len()].copy_from_slice(operand);
+    Some(decode_bytes(raw, addr, info))
+}
+
+/// Decodes the instruction at `addr`.
+pub fn decode<B: DebugBus + ?Sized>(bus: &B, addr: u16, variant: Variant) -> Disassembled {
+    let info = opcode_info(variant, bus.peek(addr));
+    let mut raw = [0; 3];
+    for (n, byte) in raw.iter_mut().enumerate().take(info.len as usize) {
+        *byte = bus.peek(addr.wrapping_add(n as u16));
+    }
+    decode_bytes(raw, addr, info)
+}
+
+/// Decodes the instruction at the CPU's PC and resolves its addresses
+/// with the current X, Y and memory, as the CPU would if it ran it next.
+pub fn decode_at<B: DebugBus + ?Sized>(cpu: &Cpu, bus: &B) -> Disassembled {
+    let mut d = decode(bus, cpu.pc, cpu.variant);
+    let peek16 = |lo: u16, hi: u16| u16::from_le_bytes([bus.peek(lo), bus.peek(hi)]);
+    // zero page pointers wrap within the page
+    let zp_pointer = |zp: u8| peek16(zp as u16, zp.wrapping_add(1) as u16);
+    let (zp, abs) = (d.zp(), d.abs());
+    match d.info.mode {
+        AddrMode::ZeroPageX => d.effective = Some(zp.wrapping_add(cpu.x) as u16),
+        AddrMode::ZeroPageY => d.effective = Some(zp.wrapping_add(cpu.y) as u16),
+        AddrMode::AbsoluteX => d.effective = Some(abs.wrapping_add(cpu.x as u16)),
+        AddrMode::AbsoluteY => d.effective = Some(abs.wrapping_add(cpu.y as u16)),
+        AddrMode::IndirectX => d.effective = Some(zp_pointer(zp.wrapping_add(cpu.x))),
+        AddrMode::IndirectY => d.effective = Some(zp_pointer(zp).wrapping_add(cpu.y as u16)),
+        AddrMode::ZeroPageIndirect => d.effective = Some(zp_pointer(zp)),
+        // the NMOS parts do not carry into the pointer's high byte
+        AddrMode::Indirect if cpu.variant.is_cmos() => d.target = Some(peek16(abs, abs.wrapping_add(1))),
+        AddrMode::Indirect => d.target = Some(peek16(abs, abs & 0xFF00 | (abs.wrapping_add(1) & 0x00FF))),
+        AddrMode::AbsoluteIndirectX => {
+            let ptr = abs.wrapping_add(cpu.x as u16);
+            d.target = Some(peek16(ptr, ptr.wrapping_add(1)));
+        }
+        _ => {}
+    }
+    d
+}
+
+// Fills in what the operand bytes alone determine.
+fn decode_bytes(bytes: [u8; 3], addr: u16, info: &'static OpcodeInfo) -> Disassembled {
+    let mut d = Disassembled {
+        addr,
+        info,
+        effective: None,
+        target: None,
+        bytes,
+    };
+    let jump = matches!(info.mnemonic, "JMP" | "JSR");
+    match info.mode {
+        AddrMode::ZeroPage => d.effective = Some(d.zp() as u16),
+        AddrMode::Absolute if jump => d.target = Some(d.abs()),
+        AddrMode::Absolute => d.effective = Some(d.abs()),
+        AddrMode::Relative => d.target = Some(d.next().wrapping_add(bytes[1] as i8 as u16)),
+        AddrMode::ZeroPageRelative => {
+            d.effective = Some(d.zp() as u16);
+            d.target = Some(d.next().wrapping_add(bytes[2] as i8 as u16));
+        }
+        _ => {}
+    }
+    d
+}
+
+/// Decodes `bytes`, loaded at `addr`, one instruction after another until
+/// they run out.
+pub fn listing(bytes: &[u8], addr: u16, variant: Variant) -> Listing<'_> {
+    Listing {
+        bytes,
+        addr,
+        variant,
+    }
+}
+
+/// Iterator returned by `listing`.
+pub struct Listing<'a> {
+    bytes: &'a [u8],
+    addr: u16,
+    variant: Variant,
+}
+
+impl Iterator for Listing<'_> {
+    type Item = Disassembled;
+    fn next(&mut self) -> Option<Disassembled> {
+        let d = decode_slice(self.bytes, self.addr, self.variant)?;
+        self.bytes = &self.bytes[d.info.len as usize..];
+        self.addr = d.next();
+        Some(d)
+    }
+}
@@ -0,0 +1,171 @@
+use crate::cpu::{Cpu, CpuState};
+use alloc::boxed::Box;
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::mem;
+
+/// A bus whose contents `Rewind` can capture and put back. Devices with
+/// state beyond RAM expose it as part of the same slice; its length must
+/// not change between captures.
+pub trait BusSnapshot {
+    fn memory(&self) -> &[u8];
+    fn memory_mut(&mut self) -> &mut [u8];
+}
+
+impl<const N: usize> BusSnapshot for [u8; N] {
+    fn memory(&self) -> &[u8] {
+        self
+    }
+    fn memory_mut(&mut self) -> &mut [u8] {
+        self
+    }
+}
+
+// Granularity of the memory deltas.
+const PAGE: usize = 256;
+
+struct Snapshot {
+    cpu: CpuState,
+    // Pages of memory as they were at this snapshot that the next one
+    // changed. Empty for the newest snapshot.
+    undo: Vec<(usize, Box<[u8]>)>,
+}
+
+/// Ring buffer of CPU and bus snapshots for rewinding.
+///
+/// Only the newest snapshot holds a full copy of memory. Every older one
+/// keeps the pages that changed after it, so going back undoes one delta
+/// at a time and the oldest snapshot can be dropped without touching the
+/// rest.
+pub struct Rewind {
+    interval: u64,
+    limit: usize,
+    snapshots: VecDeque<Snapshot>,
+    // memory at the newest snapshot
+    head: Vec<u8>,
+    // bytes held in undo pages
+    undo_bytes: usize,
+}
+
+impl Rewind {
+    /// A buffer that `record` fills every `interval` cycles, dropping the
+    /// oldest snapshots to stay within `limit` bytes. The newest snapshot
+    /// is kept even when it alone is over the limit.
+    pub fn new(interval: u64, limit: usize) -> Rewind {
+        Rewind {
+            interval,
+            limit,
+            snapshots: VecDeque::new(),
+            head: Vec::new(),
+            undo_bytes: 0,
+        }
+    }
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+        self.head.clear();
+        self.undo_bytes = 0;
+    }
+    /// Approximate heap bytes held by the snapshots.
+    pub fn memory_usage(&self) -> usize {
+        let undo_entries: usize = self.snapshots.iter().map(|s| s.undo.len()).sum();
+        self.head.len()
+            + self.undo_bytes
+            + self.snapshots.len() * mem::size_of::<Snapshot>()
+            + undo_entries * mem::size_of::<(usize, Box<[u8]>)>()
+    }
+    /// Cycle totals of the oldest and newest snapshots.
+    pub fn span(&self) -> Option<(u64, u64)> {
+        let oldest = self.snapshots.front()?.cpu.cycles();
+        let newest = self.snapshots.back()?.cpu.cycles();
+        Some((oldest, newest))
+    }
+    /// Captures a snapshot if `interval` cycles have passed since the
+    /// newest one. Call it between instructions or frames; returns whether
+    /// it captured.
+    pub fn record<B: BusSnapshot + ?Sized>(&mut self, cpu: &Cpu, bus: &B) -> bool {
+        if let Some(newest) = self.snapshots.back() {
+            if cpu.cycles < newest.cpu.cycles().saturating_add(self.interval) {
+                return false;
+            }
+        }
+        self.capture(cpu, bus);
+        true
+    }
+    /// Captures a snapshot now. A bus whose memory changed size starts the
+    /// buffer over.
+    pub fn capture<B: BusSnapshot + ?Sized>(&mut self, cpu: &Cpu, bus: &B) {
+        let memory = bus.memory();
+        match self.snapshots.back_mut() {
+            Some(newest) if memory.len() == self.head.len() => {
+                for (n, (old, new)) in self.head.chunks_mut(PAGE).zip(memory.chunks(PAGE)).enumerate() {
+                    if old != new {
+                        newest.undo.push((n, old.into()));
+                        self.undo_bytes += old.len();
+                        old.copy_from_slice(new);
+                    }
+                }
+            }
+            _ => {
+                self.clear();
+                self.head.extend_from_slice(memory);
+            }
+        }
+        self.snapshots.push_back(Snapshot {
+            cpu: cpu.save_state(),
+            undo: Vec::new(),
+        });
+        while self.snapshots.len() > 1 && self.memory_usage() > self.limit {
+            if let Some(oldest) = self.snapshots.pop_front() {
+                self.undo_bytes -= oldest.undo.iter().map(|(_, page)| page.len()).sum::<usize>();
+            }
+        }
+    }
+    /// Restores the newest snapshot taken at least `cycles` before the
+    /// CPU's current cycle, or the oldest one if none goes back that far,
+    /// and drops every snapshot after it. Returns how many cycles the CPU
+    /// went back, or None if the buffer holds nothing for this bus.
+    pub fn rewind_cycles<B: BusSnapshot + ?Sized>(&mut self, cpu: &mut Cpu, bus: &mut B, cycles: u64) -> Option<u64> {
+        if self.snapshots.is_empty() || bus.memory().len() != self.head.len() {
+            return None;
+        }
+        let target = cpu.cycles.saturating_sub(cycles);
+        while self.snapshots.len() > 1 && self.snapshots.back()?.cpu.cycles() > target {
+            self.snapshots.pop_back();
+            let newest = self.snapshots.back_mut()?;
+            for (n, page) in newest.undo.drain(..) {
+                self.head[n * PAGE..n * PAGE + page.len()].copy_from_slice(&page);
+                self.undo_bytes -= page.len();
+            }
+        }
+        let from = cpu.cycles;
+        bus.memory_mut().copy_from_slice(&self.head);
+        cpu.load_state(&self.snapshots.back()?.cpu).ok()?;
+        Some(from.saturating_sub(cpu.cycles))
+    }
+    /// `rewind_cycles` for `frames` frames of `cycles_per_frame` cycles.
+    pub fn rewind_frames<B: BusSnapshot + ?Sized>(
+        &mut self,
+        cpu: &mut Cpu,
+        bus: &mut B,
+        frames: u64,
+        cycles_per_frame: u64,
+    ) -> Option<u64> {
+        self.rewind_cycles(cpu, bus, frames.saturating_mul(cycles_per_frame))
+    }
+    /// `rewind_cycles` for `seconds` of a CPU clocked at `clock_hz`.
+    pub fn rewind_seconds<B: BusSnapshot + ?Sized>(
+        &mut self,
+        cpu: &mut Cpu,
+        bus: &mut B,
+        seconds: f64,
+        clock_hz: u64,
+    ) -> Option<u64> {
+        self.rewind_cycles(cpu, bus, (seconds * clock_hz as f64) as u64)
+    }
+}
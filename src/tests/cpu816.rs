@@ -0,0 +1,170 @@
+use super::{setup816, Memory24};
+use crate::{Cpu816, CpuError, RunState};
+
+// Runs `program` from $00:8000, one instruction per entry of `cycles`,
+// checking each instruction's cycle count.
+fn run(program: &[u8], cycles: &[u64], setup: impl Fn(&mut Cpu816, &mut Memory24)) -> (Cpu816, Memory24) {
+    let (mut cpu, mut mem) = setup816(program);
+    setup(&mut cpu, &mut mem);
+    for (n, &expected) in cycles.iter().enumerate() {
+        assert_eq!(cpu.run_instr(&mut mem).unwrap().cycles, expected, "instruction {}", n);
+    }
+    // the total keeps counting across instructions
+    assert_eq!(cpu.cycles, cycles.iter().sum::<u64>());
+    (cpu, mem)
+}
+
+// CLC; XCE; REP #$30 switches to native mode with 16-bit registers.
+const NATIVE16: [u8; 4] = [0x18, 0xFB, 0xC2, 0x30];
+
+fn native16(program: &[u8]) -> Vec<u8> {
+    let mut code = NATIVE16.to_vec();
+    code.extend_from_slice(program);
+    code
+}
+
+#[test]
+fn emulation_mode_runs_6502_code() {
+    // LDA #$80; STA $10; LDX #$01; INC $0F,X
+    let (cpu, mem) = run(&[0xA9, 0x80, 0x85, 0x10, 0xA2, 0x01, 0xF6, 0x0F], &[2, 3, 2, 6], |_, _| {});
+    assert!(cpu.e);
+    assert_eq!(mem.mem[0x10], 0x81);
+    // the stack stays in page 1
+    let (cpu, _) = run(&[0xA2, 0x00, 0x9A, 0x48], &[2, 2, 3], |_, _| {});
+    assert_eq!(cpu.sp, 0x01FF);
+}
+
+#[test]
+fn native_mode_16_bit_registers() {
+    // LDA #$1234; STA $2000; LDX #$ABCD; INC $2000
+    let program = native16(&[0xA9, 0x34, 0x12, 0x8D, 0x00, 0x20, 0xA2, 0xCD, 0xAB, 0xEE, 0x00, 0x20]);
+    let (cpu, mem) = run(&program, &[2, 2, 3, 3, 5, 3, 8], |_, _| {});
+    assert!(!cpu.e && cpu.m16() && cpu.x16());
+    assert_eq!((cpu.a, cpu.x), (0x1234, 0xABCD));
+    assert_eq!((mem.mem[0x2000], mem.mem[0x2001]), (0x35, 0x12));
+    // SEP #$20 drops A to 8 bits but keeps B, which XBA swaps back in
+    let program = native16(&[0xA9, 0xFF, 0x12, 0xE2, 0x20, 0x1A]);
+    let (cpu, _) = run(&program, &[2, 2, 3, 3, 3, 2], |_, _| {});
+    assert_eq!(cpu.a, 0x1200);
+    assert!(cpu.s.get_zero());
+    let program = native16(&[0xA9, 0xFF, 0x12, 0xE2, 0x20, 0xEB]);
+    let (cpu, _) = run(&program, &[2, 2, 3, 3, 3, 3], |_, _| {});
+    assert_eq!(cpu.a, 0xFF12);
+    assert!(!cpu.s.get_zero() && !cpu.s.get_negative());
+    // SEP #$10 clears the index high bytes
+    let program = native16(&[0xA0, 0x34, 0x12, 0xE2, 0x10]);
+    let (cpu, _) = run(&program, &[2, 2, 3, 3, 3], |_, _| {});
+    assert_eq!(cpu.y, 0x0034);
+}
+
+#[test]
+fn long_addressing_and_banks() {
+    // LDA $123456; LDX #$02; STA $120000,X
+    let (_, mem) = run(
+        &[0xAF, 0x56, 0x34, 0x12, 0xA2, 0x02, 0x9F, 0x00, 0x00, 0x12],
+        &[5, 2, 5],
+        |_, mem| mem.mem[0x123456] = 0x99,
+    );
+    assert_eq!(mem.mem[0x120002], 0x99);
+    // LDA [$10],Y through a 24-bit pointer
+    let (cpu, _) = run(&[0xA0, 0x01, 0xB7, 0x10], &[2, 6], |_, mem| {
+        mem.mem[0x10..0x13].copy_from_slice(&[0x00, 0x40, 0x7E]);
+        mem.mem[0x7E4001] = 0x5A;
+    });
+    assert_eq!(cpu.a, 0x5A);
+    // absolute operands use the data bank
+    let (cpu, _) = run(&[0xAD, 0x00, 0x30], &[4], |cpu, mem| {
+        cpu.dbr = 0x02;
+        mem.mem[0x023000] = 0x77;
+    });
+    assert_eq!(cpu.a, 0x77);
+}
+
+#[test]
+fn direct_page_and_stack_relative() {
+    // a misaligned direct page costs a cycle
+    let (cpu, _) = run(&[0xA5, 0x10], &[4], |cpu, mem| {
+        cpu.d = 0x0101;
+        mem.mem[0x0111] = 0x44;
+    });
+    assert_eq!(cpu.a, 0x44);
+    // PEA $BEEF; LDA $01,S
+    let program = native16(&[0xF4, 0xEF, 0xBE, 0xA3, 0x01]);
+    let (cpu, _) = run(&program, &[2, 2, 3, 5, 5], |_, _| {});
+    assert_eq!(cpu.a, 0xBEEF);
+    // PER pushes PC-relative addresses
+    let program = native16(&[0x62, 0x10, 0x00, 0x68]);
+    let (cpu, _) = run(&program, &[2, 2, 3, 6, 5], |_, _| {});
+    assert_eq!(cpu.a, 0x8007 + 0x10);
+}
+
+#[test]
+fn block_move() {
+    // LDA #$0002; LDX #$1000; LDY #$2000; MVN $00,$7E
+    let program = native16(&[0xA9, 0x02, 0x00, 0xA2, 0x00, 0x10, 0xA0, 0x00, 0x20, 0x54, 0x00, 0x7E]);
+    let (mut cpu, mut mem) = setup816(&program);
+    mem.mem[0x7E1000..0x7E1003].copy_from_slice(&[1, 2, 3]);
+    for _ in 0..6 {
+        cpu.run_instr(&mut mem).unwrap();
+    }
+    // one byte per execution until C wraps to $FFFF
+    for _ in 0..3 {
+        assert_eq!(cpu.run_instr(&mut mem).unwrap().cycles, 7);
+    }
+    assert_eq!(&mem.mem[0x2000..0x2003], &[1, 2, 3]);
+    assert_eq!((cpu.a, cpu.x, cpu.y, cpu.dbr), (0xFFFF, 0x1003, 0x2003, 0x00));
+    assert_eq!(cpu.pc, 0x8000 + program.len() as u16);
+}
+
+#[test]
+fn subroutines_and_interrupts() {
+    // JSL $018000 and RTL back
+    let (cpu, _) = run(&[0x22, 0x00, 0x80, 0x01, 0xEA], &[8, 6, 2], |_, mem| mem.mem[0x018000] = 0x6B);
+    assert_eq!((cpu.pbr, cpu.pc), (0x00, 0x8005));
+    // native COP saves the program bank and RTI restores it
+    let (cpu, mem) = run(&[0x18, 0xFB, 0x5C, 0x00, 0x90, 0x03], &[2, 2, 4, 8, 7], |_, mem| {
+        mem.mem[0x039000..0x039002].copy_from_slice(&[0x02, 0x00]);
+        mem.mem[0xFFE4..0xFFE6].copy_from_slice(&[0x00, 0xA0]);
+        mem.mem[0xA000] = 0x40;
+    });
+    assert_eq!((cpu.pbr, cpu.pc), (0x03, 0x9002));
+    assert_eq!(mem.mem[0x01FD], 0x03);
+    // emulation BRK uses $FFFE and pushes B
+    let (cpu, mem) = run(&[0x00, 0x00], &[7], |_, mem| mem.mem[0xFFFE..0x10000].copy_from_slice(&[0x00, 0xC0]));
+    assert_eq!(cpu.pc, 0xC000);
+    assert_eq!(mem.mem[0x01FB] & 0x10, 0x10);
+}
+
+#[test]
+fn decimal_mode() {
+    // SED; LDA #$1999; CLC; ADC #$0001
+    let program = native16(&[0xF8, 0xA9, 0x99, 0x19, 0x18, 0x69, 0x01, 0x00]);
+    let (cpu, _) = run(&program, &[2, 2, 3, 2, 3, 2, 3], |_, _| {});
+    assert_eq!(cpu.a, 0x2000);
+    assert!(!cpu.s.get_carry());
+    // SBC borrows across digits: $1000 - $0001 = $0999
+    let program = native16(&[0xF8, 0x38, 0xA9, 0x00, 0x10, 0xE9, 0x01, 0x00]);
+    let (cpu, _) = run(&program, &[2, 2, 3, 2, 2, 3, 3], |_, _| {});
+    assert_eq!(cpu.a, 0x0999);
+    assert!(cpu.s.get_carry());
+    // 8-bit: $99 + $01 wraps to $00 with carry and a valid Z
+    let (cpu, _) = run(&[0xF8, 0xA9, 0x99, 0x69, 0x01], &[2, 2, 2], |_, _| {});
+    assert_eq!(cpu.a, 0x00);
+    assert!(cpu.s.get_carry() && cpu.s.get_zero());
+}
+
+#[test]
+fn wai_and_stp() {
+    let (mut cpu, mut mem) = run(&[0x58, 0xCB, 0xEA], &[2, 3], |_, mem| {
+        mem.mem[0xFFFE..0x10000].copy_from_slice(&[0x00, 0xC0]);
+    });
+    assert_eq!(cpu.run_instr(&mut mem).unwrap().state, RunState::Waiting);
+    cpu.irq(&mut mem);
+    assert_eq!((cpu.state, cpu.pc), (RunState::Running, 0xC000));
+    mem.mem[0xC000] = 0xDB;
+    let halted = Err(CpuError::Halted { pc: 0xC000, opcode: 0xDB });
+    assert_eq!(cpu.run_instr(&mut mem), halted);
+    cpu.nmi(&mut mem);
+    assert_eq!(cpu.run_instr(&mut mem), halted);
+    assert_eq!(cpu.state, RunState::Stopped);
+}
@@ -0,0 +1,291 @@
+use super::{load_program, random_memory, Memory};
+use crate::{opcode_info, AddrMode, Bus, Cpu, CpuError, Variant};
+
+// Records every bus access so cycle sequences can be compared.
+pub struct TraceBus {
+    pub mem: Memory,
+    pub accesses: Vec<(u16, u8, &'static str)>,
+}
+
+impl TraceBus {
+    pub fn new() -> TraceBus {
+        TraceBus {
+            mem: Memory::new(),
+            accesses: Vec::new(),
+        }
+    }
+}
+
+impl Bus for TraceBus {
+    fn read(&mut self, addr: u16) -> u8 {
+        let val = self.mem.mem[addr as usize];
+        self.accesses.push((addr, val, "read"));
+        val
+    }
+    fn write(&mut self, addr: u16, val: u8) {
+        self.mem.mem[addr as usize] = val;
+        self.accesses.push((addr, val, "write"));
+    }
+}
+
+fn cycles_for(program: &[u8], setup: impl Fn(&mut Cpu, &mut Memory)) -> usize {
+    let mut bus = TraceBus::new();
+    let mut cpu = load_program(&mut bus.mem, program);
+    setup(&mut cpu, &mut bus.mem);
+    // STP and JAM report a halt, but their cycles still count
+    let _ = cpu.run_instr(&mut bus);
+    bus.accesses.len()
+}
+
+#[test]
+fn one_access_per_tick() {
+    let mut bus = TraceBus::new();
+    bus.mem.mem[0x0200..0x0203].copy_from_slice(&[0xFE, 0x00, 0x03]);
+    let mut cpu = Cpu::new(Some(0x0200));
+    for n in 1..=7 {
+        cpu.tick(&mut bus);
+        assert_eq!(bus.accesses.len(), n);
+    }
+    assert_eq!(bus.mem.mem[0x0300], 1);
+}
+
+#[test]
+fn documented_cycle_counts() {
+    let table: &[(&[u8], usize)] = &[
+        (&[0xA9, 0x01], 2),
+        (&[0xA5, 0x10], 3),
+        (&[0xB5, 0x10], 4),
+        (&[0xAD, 0x00, 0x03], 4),
+        (&[0xBD, 0x00, 0x03], 4),
+        (&[0x9D, 0x00, 0x03], 5),
+        (&[0xA1, 0x10], 6),
+        (&[0xB1, 0x10], 5),
+        (&[0x91, 0x10], 6),
+        (&[0x06, 0x10], 5),
+        (&[0x1E, 0x00, 0x03], 7),
+        (&[0x0A], 2),
+        (&[0x48], 3),
+        (&[0x68], 4),
+        (&[0x20, 0x00, 0x03], 6),
+        (&[0x60], 6),
+        (&[0x40], 6),
+        (&[0x00], 7),
+        (&[0x4C, 0x00, 0x03], 3),
+        (&[0x6C, 0x00, 0x03], 5),
+    ];
+    for (program, cycles) in table {
+        assert_eq!(cycles_for(program, |_, _| {}), *cycles, "{:02X?}", program);
+    }
+}
+
+#[test]
+fn page_cross_cycle_counts() {
+    assert_eq!(cycles_for(&[0xBD, 0xFF, 0x03], |cpu, _| cpu.x = 1), 5);
+    assert_eq!(cycles_for(&[0xB9, 0xFF, 0x03], |cpu, _| cpu.y = 1), 5);
+    assert_eq!(cycles_for(&[0x9D, 0xFF, 0x03], |cpu, _| cpu.x = 1), 5);
+    let indirect = |cpu: &mut Cpu, mem: &mut Memory| {
+        cpu.y = 0xFF;
+        mem.mem[0x10] = 0x80;
+    };
+    assert_eq!(cycles_for(&[0xB1, 0x10], indirect), 6);
+}
+
+#[test]
+fn branch_cycle_counts() {
+    // BNE: not taken, taken, taken across a page
+    assert_eq!(cycles_for(&[0xD0, 0x10], |cpu, _| cpu.s.set_zero(true)), 2);
+    assert_eq!(cycles_for(&[0xD0, 0x10], |cpu, _| cpu.s.set_zero(false)), 3);
+    assert_eq!(cycles_for(&[0xD0, 0x80], |cpu, _| cpu.s.set_zero(false)), 4);
+}
+
+fn trace_for(program: &[u8], setup: impl Fn(&mut Cpu, &mut Memory)) -> Vec<(u16, u8, &'static str)> {
+    let mut bus = TraceBus::new();
+    let mut cpu = load_program(&mut bus.mem, program);
+    setup(&mut cpu, &mut bus.mem);
+    cpu.run_instr(&mut bus).unwrap();
+    bus.accesses
+}
+
+#[test]
+fn rmw_writes_unmodified_value_first() {
+    // the single-step vector quoted above Cpu::ASL
+    let trace = trace_for(&[0x0E, 0x86, 0xEF], |_, mem| mem.mem[0xEF86] = 0x95);
+    assert_eq!(
+        trace,
+        vec![
+            (0x0200, 0x0E, "read"),
+            (0x0201, 0x86, "read"),
+            (0x0202, 0xEF, "read"),
+            (0xEF86, 0x95, "read"),
+            (0xEF86, 0x95, "write"),
+            (0xEF86, 0x2A, "write"),
+        ]
+    );
+}
+
+#[test]
+fn page_cross_reads_uncarried_address() {
+    let trace = trace_for(&[0xBD, 0xF0, 0x03], |cpu, _| cpu.x = 0x20);
+    assert_eq!(trace[3], (0x0310, 0, "read"));
+    assert_eq!(trace[4], (0x0410, 0, "read"));
+
+    let trace = trace_for(&[0x9D, 0x00, 0x03], |cpu, _| cpu.x = 0x20);
+    assert_eq!(trace[3], (0x0320, 0, "read"));
+    assert_eq!(trace[4], (0x0320, 0, "write"));
+}
+
+#[test]
+fn implied_and_stack_dummy_reads() {
+    let trace = trace_for(&[0xE8], |_, _| {});
+    assert_eq!(trace[1], (0x0201, 0, "read"));
+
+    let trace = trace_for(&[0x68], |cpu, _| cpu.sp = 0xF0);
+    assert_eq!(trace[1], (0x0201, 0, "read"));
+    assert_eq!(trace[2], (0x01F0, 0, "read"));
+    assert_eq!(trace[3], (0x01F1, 0, "read"));
+
+    let trace = trace_for(&[0xB5, 0x10], |cpu, _| cpu.x = 0x05);
+    assert_eq!(trace[2], (0x0010, 0, "read"));
+    assert_eq!(trace[3], (0x0015, 0, "read"));
+}
+
+// Runs every opcode of every variant with and without its indexed
+// address carrying into the next page and compares the cycles with
+// `opcode_info`.
+#[test]
+fn every_opcode_matches_opcode_info() {
+    let variants = [Variant::Nmos6502, Variant::Ricoh2A03, Variant::Cmos65C02, Variant::W65C02S];
+    for &variant in variants.iter() {
+        for opcode in 0..=0xFFu8 {
+            let info = opcode_info(variant, opcode);
+            if matches!(info.mode, AddrMode::Relative | AddrMode::ZeroPageRelative) {
+                continue;
+            }
+            let cycles = cycles_for(&[opcode, 0xFF, 0x03], |cpu, _| {
+                cpu.variant = variant;
+                cpu.s.set(0x00);
+            });
+            assert_eq!(cycles, info.cycles as usize, "{:?} {:02X}", variant, opcode);
+            // $03FF,X and $03FF,Y carry, and so does ($FF),Y with the
+            // pointer at $0080
+            let crossed = cycles_for(&[opcode, 0xFF, 0x03], |cpu, mem| {
+                cpu.variant = variant;
+                cpu.s.set(0x00);
+                cpu.x = 0xFF;
+                cpu.y = 0xFF;
+                mem.mem[0xFF] = 0x80;
+            });
+            let penalty = info.page_cross as usize;
+            assert_eq!(crossed, info.cycles as usize + penalty, "{:?} {:02X} across a page", variant, opcode);
+        }
+    }
+}
+
+#[test]
+fn cmos_timing_differences() {
+    let cmos = |cpu: &mut Cpu, _: &mut Memory| {
+        cpu.variant = Variant::Cmos65C02;
+        cpu.x = 0x01;
+    };
+    // BRA, with and without a page cross
+    assert_eq!(cycles_for(&[0x80, 0x10], cmos), 3);
+    assert_eq!(cycles_for(&[0x80, 0xF0], cmos), 4);
+    // ASL abs,X only pays for the fixup when the index carries
+    assert_eq!(cycles_for(&[0x1E, 0x00, 0x03], cmos), 6);
+    assert_eq!(cycles_for(&[0x1E, 0xFF, 0x03], cmos), 7);
+    // decimal ADC takes an extra cycle
+    assert_eq!(cycles_for(&[0x69, 0x01], |cpu, mem| {
+        cmos(cpu, mem);
+        cpu.s.set_decimal(true);
+    }), 3);
+    assert_eq!(cycles_for(&[0x7D, 0xFF, 0x03], |cpu, mem| {
+        cmos(cpu, mem);
+        cpu.s.set_decimal(true);
+    }), 6);
+    // read-modify-write reads the operand twice instead of writing it twice
+    let trace = trace_for(&[0xE6, 0x10], |cpu, mem| {
+        cmos(cpu, mem);
+        mem.mem[0x10] = 0x41;
+    });
+    assert_eq!(&trace[2..], &[(0x0010, 0x41, "read"), (0x0010, 0x41, "read"), (0x0010, 0x42, "write")]);
+    // the page-cross fixup re-reads the high operand byte
+    let trace = trace_for(&[0xBD, 0xFF, 0x03], cmos);
+    assert_eq!(trace[3], (0x0202, 0x03, "read"));
+}
+
+#[test]
+fn w65c02s_cycle_counts() {
+    let wdc = |cpu: &mut Cpu, mem: &mut Memory| {
+        cpu.variant = Variant::W65C02S;
+        mem.mem[0x10] = 0x01;
+    };
+    // RMB0/SMB0
+    assert_eq!(cycles_for(&[0x07, 0x10], wdc), 5);
+    assert_eq!(cycles_for(&[0x87, 0x10], wdc), 5);
+    // BBR0 not taken, BBS0 taken, BBS0 taken across a page
+    assert_eq!(cycles_for(&[0x0F, 0x10, 0x10], wdc), 5);
+    assert_eq!(cycles_for(&[0x8F, 0x10, 0x10], wdc), 6);
+    assert_eq!(cycles_for(&[0x8F, 0x10, 0xF0], wdc), 7);
+    // WAI and STP take three cycles before the bus goes idle
+    assert_eq!(cycles_for(&[0xCB], wdc), 3);
+    assert_eq!(cycles_for(&[0xDB], wdc), 3);
+}
+
+#[test]
+fn opcode_table_matches_the_core() {
+    let control_flow = ["BRK", "JSR", "RTS", "RTI", "JMP", "JAM", "WAI", "STP"];
+    let mut seed = 0x9E37_79B9u32;
+    for &variant in [Variant::Nmos6502, Variant::Cmos65C02, Variant::W65C02S].iter() {
+        for opcode in 0..=0xFFu8 {
+            let info = opcode_info(variant, opcode);
+            let branch = matches!(info.mode, AddrMode::Relative | AddrMode::ZeroPageRelative);
+            if branch || control_flow.contains(&info.mnemonic) {
+                continue;
+            }
+            // random registers and memory: PC moves by the length and only
+            // the listed flags change
+            for _ in 0..4 {
+                let mut mem = random_memory(&mut seed);
+                mem.mem[0x0200] = opcode;
+                let r = mem.mem[0x0100..0x0105].to_vec();
+                let mut cpu = Cpu::new_test(0x0200, r[0], r[1], r[2], r[3], r[4]);
+                cpu.variant = variant;
+                cpu.run_instr(&mut mem).unwrap();
+                assert_eq!(cpu.pc, 0x0200 + info.len as u16, "{:?} {:02X}", variant, opcode);
+                let changed = (r[4] ^ cpu.s.get()) & 0xCF;
+                assert_eq!(changed & !info.flags, 0, "{:?} {:02X}", variant, opcode);
+            }
+        }
+    }
+}
+
+#[test]
+fn run_for_cycles() {
+    let mut mem = Memory::new();
+    mem.mem[0x0200..0x0300].copy_from_slice(&[0xEA; 0x100]);
+    let mut cpu = Cpu::new(Some(0x0200));
+    // four NOPs overshoot a 7-cycle budget by one, which the next budget
+    // gives back
+    assert_eq!(cpu.run_cycles(&mut mem, 7), Ok(1));
+    assert_eq!(cpu.run_cycles(&mut mem, 7 - 1), Ok(0));
+    assert_eq!((cpu.cycles, cpu.pc), (14, 0x0207));
+    assert_eq!(cpu.run_until(&mut mem, |cpu| cpu.pc == 0x0210), Ok(18));
+    assert_eq!(cpu.run_until(&mut mem, |cpu| cpu.pc == 0x0210), Ok(0));
+    assert_eq!(cpu.cycles, 32);
+    // a halt ends the budget early
+    mem.mem[0x0212] = 0x02;
+    let halted = Err(CpuError::Halted { pc: 0x0212, opcode: 0x02 });
+    assert_eq!(cpu.run_cycles(&mut mem, 100), halted);
+    // an unbounded budget runs until the halt too
+    assert_eq!(cpu.run_cycles(&mut mem, u64::MAX), halted);
+}
+
+#[test]
+fn dyn_bus_still_works() {
+    let mut bus = TraceBus::new();
+    bus.mem.mem[0x0200..0x0203].copy_from_slice(&[0xEE, 0x00, 0x03]);
+    let mut cpu = Cpu::new(Some(0x0200));
+    let dyn_bus: &mut dyn Bus = &mut bus;
+    assert_eq!(cpu.run_instr(dyn_bus).unwrap().cycles, 6);
+    assert_eq!(bus.mem.mem[0x0300], 1);
+}
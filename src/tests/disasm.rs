@@ -0,0 +1,106 @@
+use super::Memory;
+use crate::disasm::{decode, decode_at, decode_slice, listing};
+use crate::{Cpu, Variant};
+
+fn text(bytes: &[u8], variant: Variant) -> String {
+    decode_slice(bytes, 0x0200, variant).unwrap().to_string()
+}
+
+#[test]
+fn operand_syntax() {
+    let nmos = Variant::Nmos6502;
+    assert_eq!(text(&[0xEA], nmos), "NOP");
+    assert_eq!(text(&[0x0A], nmos), "ASL A");
+    assert_eq!(text(&[0xA9, 0x10], nmos), "LDA #$10");
+    assert_eq!(text(&[0xB5, 0x44], nmos), "LDA $44,X");
+    assert_eq!(text(&[0xB6, 0x44], nmos), "LDX $44,Y");
+    assert_eq!(text(&[0xBD, 0x00, 0x44], nmos), "LDA $4400,X");
+    assert_eq!(text(&[0xA1, 0x20], nmos), "LDA ($20,X)");
+    assert_eq!(text(&[0xB1, 0x20], nmos), "LDA ($20),Y");
+    assert_eq!(text(&[0x6C, 0x34, 0x12], nmos), "JMP ($1234)");
+    let cmos = Variant::W65C02S;
+    assert_eq!(text(&[0xB2, 0x20], cmos), "LDA ($20)");
+    assert_eq!(text(&[0x7C, 0x34, 0x12], cmos), "JMP ($1234,X)");
+    assert_eq!(text(&[0x80, 0xFE], cmos), "BRA $0200");
+    assert_eq!(text(&[0x8F, 0x12, 0x03], cmos), "BBS0 $12,$0206");
+    // the same byte decodes per variant
+    assert_eq!(text(&[0xB2, 0x20], nmos), "JAM");
+    assert_eq!(text(&[0x1A], Variant::Cmos65C02), "INC A");
+    assert_eq!(text(&[0x07, 0x12], Variant::Cmos65C02), "NOP");
+}
+
+#[test]
+fn records_addresses_and_targets() {
+    let d = decode_slice(&[0x8D, 0x00, 0x44], 0x0200, Variant::Nmos6502).unwrap();
+    assert_eq!((d.addr, d.bytes(), d.effective, d.target), (0x0200, &[0x8D, 0x00, 0x44][..], Some(0x4400), None));
+    assert_eq!(d.next(), 0x0203);
+    let d = decode_slice(&[0x20, 0x00, 0xC0], 0x0200, Variant::Nmos6502).unwrap();
+    assert_eq!((d.effective, d.target), (None, Some(0xC000)));
+    let d = decode_slice(&[0xD0, 0x80], 0x0200, Variant::Nmos6502).unwrap();
+    assert_eq!(d.target, Some(0x0182));
+    assert!(decode_slice(&[0xAD, 0x00], 0x0200, Variant::Nmos6502).is_none());
+    assert!(decode_slice(&[], 0x0200, Variant::Nmos6502).is_none());
+    // undocumented opcodes are marked
+    let d = decode_slice(&[0xA7, 0x10], 0x0200, Variant::Nmos6502).unwrap();
+    assert_eq!((d.mnemonic(), d.is_official()), ("LAX", false));
+}
+
+#[test]
+fn resolves_with_registers_and_memory() {
+    let mut mem = Memory::new();
+    let mut cpu = Cpu::new(Some(0x0200));
+    cpu.x = 0x04;
+    cpu.y = 0x10;
+    mem.mem[0x20..0x22].copy_from_slice(&[0x00, 0x30]);
+    mem.mem[0x24..0x26].copy_from_slice(&[0x80, 0x40]);
+    mem.mem[0xFF] = 0x11;
+    mem.mem[0x00] = 0x22;
+    let cases: [(&[u8], u16); 6] = [
+        (&[0xB5, 0xFE], 0x0002),
+        (&[0xBD, 0xFE, 0x44], 0x4502),
+        (&[0xA1, 0x20], 0x4080),
+        (&[0xB1, 0x20], 0x3010),
+        (&[0xB9, 0x00, 0x44], 0x4410),
+        (&[0xB1, 0xFF], 0x2221),
+    ];
+    for (bytes, effective) in cases {
+        mem.mem[0x0200..0x0200 + bytes.len()].copy_from_slice(bytes);
+        assert_eq!(decode_at(&cpu, &mem).effective, Some(effective), "{:02X?}", bytes);
+    }
+    // NMOS JMP ($xxFF) takes the high byte from the start of the page
+    mem.mem[0x0200..0x0203].copy_from_slice(&[0x6C, 0xFF, 0x10]);
+    mem.mem[0x10FF] = 0x34;
+    mem.mem[0x1000] = 0x12;
+    mem.mem[0x1100] = 0x56;
+    assert_eq!(decode_at(&cpu, &mem).target, Some(0x1234));
+    cpu.variant = Variant::Cmos65C02;
+    assert_eq!(decode_at(&cpu, &mem).target, Some(0x5634));
+}
+
+#[test]
+fn covers_every_opcode() {
+    for variant in [Variant::Nmos6502, Variant::Ricoh2A03, Variant::Cmos65C02, Variant::W65C02S] {
+        for opcode in 0..=255u8 {
+            let mut mem = Memory::new();
+            mem.mem[0x0200..0x0203].copy_from_slice(&[opcode, 0x12, 0x34]);
+            let d = decode(&mem, 0x0200, variant);
+            assert_eq!(d.bytes().len(), d.info.len as usize);
+            assert_eq!(d.bytes()[0], opcode);
+            assert!(d.to_string().starts_with(d.mnemonic()));
+            // the 65C02 documents nothing it does not implement
+            if variant.is_cmos() && d.mnemonic() != "NOP" {
+                assert!(d.is_official(), "{:?} ${:02X}", variant, opcode);
+            }
+        }
+    }
+}
+
+#[test]
+fn lists_a_program() {
+    // LDX #$00; loop: INC $0300,X; INX; BNE loop; RTS
+    let program = [0xA2, 0x00, 0xFE, 0x00, 0x03, 0xE8, 0xD0, 0xFA, 0x60];
+    let lines: Vec<String> = listing(&program, 0x0200, Variant::Nmos6502)
+        .map(|d| format!("{:04X} {}", d.addr, d))
+        .collect();
+    assert_eq!(lines, ["0200 LDX #$00", "0202 INC $0300,X", "0205 INX", "0206 BNE $0202", "0208 RTS"]);
+}
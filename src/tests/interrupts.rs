@@ -0,0 +1,374 @@
+use super::cycles::TraceBus;
+use super::setup_traced;
+use crate::{RunState, Variant};
+
+fn pushed_pc(bus: &TraceBus) -> u16 {
+    u16::from_le_bytes([bus.mem.mem[0x01FC], bus.mem.mem[0x01FD]])
+}
+
+#[test]
+fn irq_sequence() {
+    let (mut cpu, mut bus) = setup_traced(&[0xEA]);
+    cpu.set_irq_line(true);
+    cpu.run_instr(&mut bus).unwrap();
+    bus.accesses.clear();
+    assert_eq!(cpu.run_instr(&mut bus).unwrap().cycles, 7);
+    assert_eq!(cpu.pc, 0xA000);
+    assert!(cpu.s.get_interrupt());
+    assert_eq!(
+        bus.accesses,
+        vec![
+            (0x0201, 0, "read"),
+            (0x0201, 0, "read"),
+            (0x01FD, 0x02, "write"),
+            (0x01FC, 0x01, "write"),
+            (0x01FB, 0x21, "write"),
+            (0xFFFE, 0x00, "read"),
+            (0xFFFF, 0xA0, "read"),
+        ]
+    );
+}
+
+#[test]
+fn masked_irq_is_ignored() {
+    let (mut cpu, mut bus) = setup_traced(&[0xEA, 0xEA]);
+    cpu.s.set_interrupt(true);
+    cpu.set_irq_line(true);
+    cpu.run_instr(&mut bus).unwrap();
+    cpu.run_instr(&mut bus).unwrap();
+    assert_eq!(cpu.pc, 0x0202);
+}
+
+#[test]
+fn irq_sources_are_wired_or() {
+    // the handler is NOP; RTI and the test releases one source per call
+    let (mut cpu, mut bus) = setup_traced(&[0xEA, 0xEA]);
+    bus.mem.mem[0xA000..0xA002].copy_from_slice(&[0xEA, 0x40]);
+    cpu.assert_irq(1);
+    cpu.assert_irq(5);
+    assert!(cpu.irq_sources().contains(1) && cpu.irq_sources().contains(5));
+    assert_eq!(cpu.irq_sources().iter().collect::<Vec<_>>(), vec![1, 5]);
+    cpu.run_instr(&mut bus).unwrap();
+    cpu.run_instr(&mut bus).unwrap();
+    assert_eq!(cpu.pc, 0xA000);
+    // releasing one source leaves the line asserted
+    cpu.release_irq(1);
+    cpu.run_instr(&mut bus).unwrap();
+    cpu.run_instr(&mut bus).unwrap();
+    cpu.run_instr(&mut bus).unwrap();
+    assert_eq!(cpu.pc, 0xA000);
+    assert_eq!(cpu.irq_sources().bits(), 1 << 5);
+    // and releasing the last lets the program continue
+    cpu.release_irq(5);
+    cpu.run_instr(&mut bus).unwrap();
+    cpu.run_instr(&mut bus).unwrap();
+    cpu.run_instr(&mut bus).unwrap();
+    assert_eq!(cpu.pc, 0x0202);
+    assert!(cpu.irq_sources().is_empty());
+}
+
+#[test]
+fn set_irq_line_drives_source_zero() {
+    let (mut cpu, _) = setup_traced(&[]);
+    cpu.set_irq_line(true);
+    cpu.assert_irq(2);
+    assert_eq!(cpu.irq_sources().bits(), 0b101);
+    cpu.set_irq_line(false);
+    assert_eq!(cpu.irq_sources().bits(), 0b100);
+}
+
+#[test]
+fn out_of_range_irq_source_is_rejected() {
+    let (mut cpu, _) = setup_traced(&[]);
+    assert!(!cpu.assert_irq(32));
+    assert!(!cpu.release_irq(255));
+    assert!(cpu.irq_sources().is_empty());
+    assert!(cpu.assert_irq(31));
+    assert_eq!(cpu.irq_sources().bits(), 1 << 31);
+}
+
+#[test]
+fn nmi_is_edge_triggered() {
+    let (mut cpu, mut bus) = setup_traced(&[0xEA]);
+    cpu.s.set(0x35);
+    cpu.set_nmi_line(true);
+    cpu.run_instr(&mut bus).unwrap();
+    cpu.run_instr(&mut bus).unwrap();
+    assert_eq!(cpu.pc, 0x9000);
+    // B stays clear in the pushed status even when the live P has bit 4 set
+    assert_eq!(bus.mem.mem[0x01FB], 0x25);
+    // holding the line does not retrigger
+    bus.mem.mem[0x9000..0x9003].copy_from_slice(&[0xEA, 0xEA, 0xEA]);
+    cpu.run_instr(&mut bus).unwrap();
+    cpu.run_instr(&mut bus).unwrap();
+    assert_eq!(cpu.pc, 0x9002);
+    // a new edge does
+    cpu.set_nmi_line(false);
+    cpu.set_nmi_line(true);
+    cpu.run_instr(&mut bus).unwrap();
+    cpu.run_instr(&mut bus).unwrap();
+    assert_eq!(cpu.pc, 0x9000);
+}
+
+#[test]
+fn irq_on_the_last_cycle_waits_an_instruction() {
+    // LDA $0300; NOP
+    let (mut cpu, mut bus) = setup_traced(&[0xAD, 0x00, 0x03, 0xEA]);
+    for _ in 0..3 {
+        cpu.tick(&mut bus);
+    }
+    cpu.set_irq_line(true);
+    cpu.run_instr(&mut bus).unwrap();
+    cpu.run_instr(&mut bus).unwrap();
+    assert_eq!(cpu.pc, 0x0204);
+    cpu.run_instr(&mut bus).unwrap();
+    assert_eq!((cpu.pc, pushed_pc(&bus)), (0xA000, 0x0204));
+}
+
+#[test]
+fn cli_takes_effect_after_the_next_instruction() {
+    // CLI; NOP
+    let (mut cpu, mut bus) = setup_traced(&[0x58, 0xEA]);
+    cpu.s.set_interrupt(true);
+    cpu.set_irq_line(true);
+    cpu.run_instr(&mut bus).unwrap();
+    cpu.run_instr(&mut bus).unwrap();
+    assert_eq!(cpu.pc, 0x0202);
+    cpu.run_instr(&mut bus).unwrap();
+    assert_eq!((cpu.pc, pushed_pc(&bus)), (0xA000, 0x0202));
+}
+
+#[test]
+fn irq_can_fire_right_after_sei() {
+    // SEI; NOP
+    let (mut cpu, mut bus) = setup_traced(&[0x78, 0xEA]);
+    cpu.set_irq_line(true);
+    cpu.run_instr(&mut bus).unwrap();
+    cpu.run_instr(&mut bus).unwrap();
+    assert_eq!((cpu.pc, pushed_pc(&bus)), (0xA000, 0x0201));
+    // the pushed status already has I set
+    assert_eq!(bus.mem.mem[0x01FB] & 0x04, 0x04);
+}
+
+#[test]
+fn taken_branch_delays_irq() {
+    // BNE +0; NOP
+    let (mut cpu, mut bus) = setup_traced(&[0xD0, 0x00, 0xEA]);
+    cpu.tick(&mut bus);
+    cpu.set_irq_line(true);
+    cpu.run_instr(&mut bus).unwrap();
+    assert_eq!(cpu.pc, 0x0202);
+    cpu.run_instr(&mut bus).unwrap();
+    assert_eq!(cpu.pc, 0x0203);
+    cpu.run_instr(&mut bus).unwrap();
+    assert_eq!((cpu.pc, pushed_pc(&bus)), (0xA000, 0x0203));
+    // an ordinary three-cycle instruction polls on its second cycle
+    let (mut cpu, mut bus) = setup_traced(&[0xA5, 0x00, 0xEA]);
+    cpu.tick(&mut bus);
+    cpu.set_irq_line(true);
+    cpu.run_instr(&mut bus).unwrap();
+    cpu.run_instr(&mut bus).unwrap();
+    assert_eq!((cpu.pc, pushed_pc(&bus)), (0xA000, 0x0202));
+}
+
+#[test]
+fn handler_runs_one_instruction_before_the_next_interrupt() {
+    let (mut cpu, mut bus) = setup_traced(&[0xEA]);
+    bus.mem.mem[0xA000] = 0x58;
+    cpu.set_irq_line(true);
+    cpu.run_instr(&mut bus).unwrap();
+    // NMI arrives on the last cycle of the IRQ sequence
+    for _ in 0..6 {
+        cpu.tick(&mut bus);
+    }
+    cpu.set_nmi_line(true);
+    cpu.tick(&mut bus);
+    assert_eq!(cpu.pc, 0xA000);
+    cpu.run_instr(&mut bus).unwrap();
+    assert_eq!(cpu.pc, 0xA001);
+    cpu.run_instr(&mut bus).unwrap();
+    assert_eq!(cpu.pc, 0x9000);
+}
+
+#[test]
+fn cmos_interrupts_clear_decimal() {
+    let (mut cpu, mut bus) = setup_traced(&[0xEA]);
+    cpu.variant = Variant::Cmos65C02;
+    cpu.s.set_decimal(true);
+    cpu.set_irq_line(true);
+    cpu.run_instr(&mut bus).unwrap();
+    cpu.run_instr(&mut bus).unwrap();
+    assert!(!cpu.s.get_decimal());
+    let (mut cpu, mut bus) = setup_traced(&[0xEA]);
+    cpu.s.set_decimal(true);
+    cpu.set_irq_line(true);
+    cpu.run_instr(&mut bus).unwrap();
+    cpu.run_instr(&mut bus).unwrap();
+    assert!(cpu.s.get_decimal());
+}
+
+#[test]
+fn brk_pushes_b_and_skips_signature() {
+    let (mut cpu, mut bus) = setup_traced(&[0x00]);
+    assert_eq!(cpu.run_instr(&mut bus).unwrap().cycles, 7);
+    assert_eq!(cpu.pc, 0xA000);
+    assert_eq!(bus.mem.mem[0x01FB], 0x31);
+    assert_eq!(pushed_pc(&bus), 0x0202);
+}
+
+#[test]
+fn nmi_hijacks_brk() {
+    let (mut cpu, mut bus) = setup_traced(&[0x00]);
+    bus.mem.mem[0x9000..0x9002].copy_from_slice(&[0xEA, 0xEA]);
+    // NMI arrives while PCL is pushed, before the vector is chosen
+    for _ in 0..3 {
+        cpu.tick(&mut bus);
+    }
+    cpu.set_nmi_line(true);
+    cpu.run_instr(&mut bus).unwrap();
+    assert_eq!(cpu.pc, 0x9000);
+    assert_eq!(bus.mem.mem[0x01FB], 0x31);
+    assert_eq!(pushed_pc(&bus), 0x0202);
+    // the edge is consumed
+    cpu.run_instr(&mut bus).unwrap();
+    cpu.run_instr(&mut bus).unwrap();
+    assert_eq!(cpu.pc, 0x9002);
+}
+
+#[test]
+fn nmi_hijacks_irq() {
+    let (mut cpu, mut bus) = setup_traced(&[0xEA]);
+    cpu.set_irq_line(true);
+    cpu.run_instr(&mut bus).unwrap();
+    for _ in 0..3 {
+        cpu.tick(&mut bus);
+    }
+    cpu.set_nmi_line(true);
+    cpu.run_instr(&mut bus).unwrap();
+    assert_eq!(cpu.pc, 0x9000);
+    assert_eq!(bus.mem.mem[0x01FB], 0x21);
+    // too late to hijack: the IRQ handler runs one instruction first
+    let (mut cpu, mut bus) = setup_traced(&[0xEA]);
+    bus.mem.mem[0xA000] = 0xEA;
+    cpu.set_irq_line(true);
+    cpu.run_instr(&mut bus).unwrap();
+    for _ in 0..5 {
+        cpu.tick(&mut bus);
+    }
+    cpu.set_nmi_line(true);
+    cpu.run_instr(&mut bus).unwrap();
+    assert_eq!(cpu.pc, 0xA000);
+    cpu.run_instr(&mut bus).unwrap();
+    cpu.run_instr(&mut bus).unwrap();
+    assert_eq!(cpu.pc, 0x9000);
+}
+
+#[test]
+fn cmos_brk_is_not_hijacked() {
+    let (mut cpu, mut bus) = setup_traced(&[0x00]);
+    cpu.variant = Variant::Cmos65C02;
+    bus.mem.mem[0xA000] = 0xEA;
+    for _ in 0..3 {
+        cpu.tick(&mut bus);
+    }
+    cpu.set_nmi_line(true);
+    cpu.run_instr(&mut bus).unwrap();
+    assert_eq!(cpu.pc, 0xA000);
+    cpu.run_instr(&mut bus).unwrap();
+    cpu.run_instr(&mut bus).unwrap();
+    assert_eq!(cpu.pc, 0x9000);
+}
+
+#[test]
+fn power_on_and_reset() {
+    let (mut cpu, mut bus) = setup_traced(&[0xEA]);
+    bus.mem.mem[0xFFFC..0xFFFE].copy_from_slice(&[0x00, 0x02]);
+    cpu.pc = 0x1234;
+    cpu.cycles = 0;
+    cpu.power_on(&mut bus);
+    assert_eq!(cpu.cycles, 7);
+    assert_eq!((cpu.pc, cpu.sp, cpu.s.get()), (0x0200, 0xFD, 0x34));
+    // the three pushes become reads
+    assert_eq!(
+        bus.accesses,
+        vec![
+            (0x1234, 0, "read"),
+            (0x1234, 0, "read"),
+            (0x0100, 0, "read"),
+            (0x01FF, 0, "read"),
+            (0x01FE, 0, "read"),
+            (0xFFFC, 0x00, "read"),
+            (0xFFFD, 0x02, "read"),
+        ]
+    );
+    // a warm reset keeps the registers but still drops SP by 3
+    cpu.a = 0x42;
+    cpu.s.set(0x20);
+    cpu.reset(&mut bus);
+    assert_eq!((cpu.a, cpu.sp, cpu.pc), (0x42, 0xFA, 0x0200));
+    assert!(cpu.s.get_interrupt());
+}
+
+#[test]
+fn reset_line_interrupts_an_instruction() {
+    // LDA $0300
+    let (mut cpu, mut bus) = setup_traced(&[0xAD, 0x00, 0x03]);
+    bus.mem.mem[0xFFFC..0xFFFE].copy_from_slice(&[0x00, 0x04]);
+    cpu.tick(&mut bus);
+    cpu.tick(&mut bus);
+    cpu.set_reset_line(true);
+    bus.accesses.clear();
+    // held in reset, the bus stays idle
+    for _ in 0..4 {
+        cpu.tick(&mut bus);
+    }
+    assert!(bus.accesses.is_empty());
+    cpu.set_reset_line(false);
+    assert_eq!(cpu.run_instr(&mut bus).unwrap().cycles, 7);
+    assert_eq!(cpu.pc, 0x0400);
+    // it also ends STP
+    let (mut cpu, mut bus) = setup_traced(&[0xDB]);
+    bus.mem.mem[0xFFFC..0xFFFE].copy_from_slice(&[0x00, 0x04]);
+    cpu.variant = Variant::W65C02S;
+    assert!(cpu.run_instr(&mut bus).is_err());
+    assert_eq!(cpu.state, RunState::Stopped);
+    cpu.reset(&mut bus);
+    assert_eq!((cpu.state, cpu.pc), (RunState::Running, 0x0400));
+}
+
+#[test]
+fn run_instr_returns_while_reset_is_held() {
+    // LDA $0200, with RESET asserted after the opcode fetch
+    let (mut cpu, mut bus) = setup_traced(&[0xAD, 0x00, 0x02]);
+    bus.mem.mem[0xFFFC..0xFFFE].copy_from_slice(&[0x00, 0x04]);
+    cpu.tick(&mut bus);
+    cpu.set_reset_line(true);
+    for _ in 0..3 {
+        let outcome = cpu.run_instr(&mut bus).unwrap();
+        assert_eq!((outcome.cycles, outcome.state), (1, RunState::Reset));
+    }
+    cpu.set_reset_line(false);
+    let outcome = cpu.run_instr(&mut bus).unwrap();
+    assert_eq!((outcome.cycles, outcome.state), (7, RunState::Running));
+    assert_eq!(cpu.pc, 0x0400);
+}
+
+#[test]
+fn wai_takes_an_unmasked_interrupt_before_the_next_instruction() {
+    for (nmi, vector) in [(false, 0xA000), (true, 0x9000)] {
+        // WAI; INX
+        let (mut cpu, mut bus) = setup_traced(&[0xCB, 0xE8]);
+        cpu.variant = Variant::W65C02S;
+        assert_eq!(cpu.run_instr(&mut bus).unwrap().state, RunState::Waiting);
+        assert_eq!(cpu.run_instr(&mut bus).unwrap().cycles, 1);
+        if nmi {
+            cpu.set_nmi_line(true);
+        } else {
+            cpu.set_irq_line(true);
+        }
+        assert_eq!(cpu.run_instr(&mut bus).unwrap().state, RunState::Running);
+        assert_eq!(cpu.run_instr(&mut bus).unwrap().cycles, 7);
+        assert_eq!((cpu.pc, cpu.x), (vector, 0), "nmi: {}", nmi);
+        assert_eq!(pushed_pc(&bus), 0x0201, "nmi: {}", nmi);
+    }
+}
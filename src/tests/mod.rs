@@ -1,10 +1,19 @@
+pub mod cpu816;
+pub mod cycles;
+pub mod disasm;
+pub mod interrupts;
+pub mod nestest;
+pub mod opcodes;
+pub mod rewind;
+pub mod save_state;
 pub mod single_step;
-use crate::Cpu;
+pub mod time_travel;
+use crate::{BusSnapshot, Cpu, Cpu816, RunState, Variant};
+use cycles::TraceBus;
 use core::ops::{Index, IndexMut};
 use single_step::Root2;
-use std::fmt::format;
 use std::fs;
-//use std::slice::range;
+use std::path::Path;
 
 #[derive(Debug, Ord, PartialOrd, Eq, PartialEq, Copy, Clone)]
 pub struct Memory {
@@ -30,63 +39,198 @@ impl IndexMut<u16> for Memory {
     }
 }
 
+// Flat 16 MiB address space.
+pub struct Memory24 {
+    pub mem: Vec<u8>,
+}
+
+impl Memory24 {
+    pub fn new() -> Memory24 {
+        Memory24 { mem: vec![0; 1 << 24] }
+    }
+}
+
+impl Index<u32> for Memory24 {
+    type Output = u8;
+    fn index(&self, index: u32) -> &Self::Output {
+        &self.mem[index as usize]
+    }
+}
+
+impl IndexMut<u32> for Memory24 {
+    fn index_mut(&mut self, index: u32) -> &mut Self::Output {
+        &mut self.mem[index as usize]
+    }
+}
+
+// Memory full of xorshift32 noise, advancing `seed` so successive calls
+// differ.
+pub fn random_memory(seed: &mut u32) -> Memory {
+    let mut mem = Memory::new();
+    for byte in mem.mem.iter_mut() {
+        *seed ^= *seed << 13;
+        *seed ^= *seed >> 17;
+        *seed ^= *seed << 5;
+        *byte = *seed as u8;
+    }
+    mem
+}
+
+// Copies `program` to $0200 and returns a CPU that starts there.
+pub fn load_program(mem: &mut Memory, program: &[u8]) -> Cpu {
+    mem.mem[0x0200..0x0200 + program.len()].copy_from_slice(program);
+    Cpu::new(Some(0x0200))
+}
+
+// Memory holding `program` at $0200, an NMI handler at $9000 (INC $20;
+// RTI) and an IRQ handler at $A000 (INC $21; RTI), and a CPU about to run
+// the program with P at $21 so IRQs are taken.
+pub fn setup(program: &[u8]) -> (Cpu, Memory) {
+    let mut mem = Memory::new();
+    mem.mem[0x9000..0x9003].copy_from_slice(&[0xE6, 0x20, 0x40]);
+    mem.mem[0xA000..0xA003].copy_from_slice(&[0xE6, 0x21, 0x40]);
+    mem.mem[0xFFFA..0x10000].copy_from_slice(&[0x00, 0x90, 0x00, 0x00, 0x00, 0xA0]);
+    let mut cpu = load_program(&mut mem, program);
+    cpu.s.set(0x20 | 0x01);
+    (cpu, mem)
+}
+
+// `setup` on a bus that records every access.
+pub fn setup_traced(program: &[u8]) -> (Cpu, TraceBus) {
+    let (cpu, mem) = setup(program);
+    let mut bus = TraceBus::new();
+    bus.mem = mem;
+    (cpu, bus)
+}
+
+// A 65C816 about to run `program` at $00:8000.
+pub fn setup816(program: &[u8]) -> (Cpu816, Memory24) {
+    let mut mem = Memory24::new();
+    mem.mem[0x8000..0x8000 + program.len()].copy_from_slice(program);
+    (Cpu816::new(Some(0x8000)), mem)
+}
+
+impl BusSnapshot for Memory {
+    fn memory(&self) -> &[u8] {
+        &self.mem
+    }
+    fn memory_mut(&mut self) -> &mut [u8] {
+        &mut self.mem
+    }
+}
+
 #[test]
+#[ignore = "needs SingleStepTests data in ./65x02"]
 pub fn run_tests() {
+    run_suite("./65x02/nes6502/v1", Variant::Ricoh2A03);
+}
+
+#[test]
+#[ignore = "needs SingleStepTests data in ./65x02"]
+pub fn run_tests_6502() {
+    run_suite("./65x02/6502/v1", Variant::Nmos6502);
+}
+
+#[test]
+#[ignore = "needs SingleStepTests data in ./65x02"]
+pub fn run_tests_65c02() {
+    run_suite("./65x02/synertek65c02/v1", Variant::Cmos65C02);
+}
+
+#[test]
+#[ignore = "needs SingleStepTests data in ./65x02"]
+pub fn run_tests_w65c02s() {
+    run_suite("./65x02/wdc65c02/v1", Variant::W65C02S);
+}
+
+fn run_suite(dir: &str, variant: Variant) {
+    let test_dir = Path::new(dir);
+    assert!(test_dir.is_dir(), "{} not found", test_dir.display());
     for i in 0..0x100 {
-        use std::env;
-        let mut memory_ut: Memory = Memory::new();
-        let mut memory_final: Memory = Memory::new();
-        let json_file_path = format!("./65x02/nes6502/v1/{:02x}.json", i);
-        println!("{}", json_file_path);
+        let json_file_path = test_dir.join(format!("{:02x}.json", i));
+        println!("{}", json_file_path.display());
         let file = fs::read(json_file_path).unwrap();
         let tests: Vec<Root2> = serde_json::from_reader(file.as_slice()).unwrap();
-        let mut test_number = 1;
-        for test in tests {
-            let mut cpu_ut = Cpu::new_test(
-                test.initial.pc as u16,
-                test.initial.s as u8,
-                test.initial.a as u8,
-                test.initial.x as u8,
-                test.initial.y as u8,
-                test.initial.p as u8,
-            );
-            for ram_value in test.initial.ram {
-                memory_ut[ram_value[0] as u16] = ram_value[1] as u8;
-            }
-            cpu_ut.run_instr(&mut memory_ut);
-
-            let cpu_final = Cpu::new_test(
-                test.final_field.pc as u16,
-                test.final_field.s as u8,
-                test.final_field.a as u8,
-                test.final_field.x as u8,
-                test.final_field.y as u8,
-                test.final_field.p as u8,
-            );
-            for ram_final in test.final_field.ram {
-                memory_final[ram_final[0] as u16] = ram_final[1] as u8;
-            }
-            if memory_ut != memory_final{
-                //println!("Memory ut {:?} != Memory final {:?}", memory_ut, memory_final);
-                for ((ut,fin), idx) in (memory_ut.mem.iter().zip(memory_final.mem.iter()).zip(0..0xFFFF)){
-                    if(*ut != *fin){
-                        println!("Memory ut {:x?} != Memory final {:x?} @ addr {:x?}", *ut, *fin, idx);
-                    }
-                }
-                assert!(false);
-            }
-            //print!("cpu_ut {:#x?}",cpu_ut);
-            //print!("cpu_final {:#x?}",cpu_final);
-            if cpu_final != cpu_ut {
-                println!("instruction {:X} under test", i);
-                println!("test number {}", test_number);
-                println!("name:{:#x?}", test.name);
-                println!("cpu_ut {:#x?}", cpu_ut);
-                println!("cpu_final {:#x?}", cpu_final);
-                assert_eq!(cpu_final, cpu_ut, "cpu_final != cpu_ut");
-                panic!("cpu_final != cpu_ut");
+        for (test_number, test) in (1..).zip(tests) {
+            println!("instruction {:X} test number {}", i, test_number);
+            run_vector(&test, variant);
+        }
+    }
+}
+
+// Runs one SingleStepTests vector and checks the registers, the memory and
+// every bus cycle against it.
+fn run_vector(test: &Root2, variant: Variant) {
+    let mut bus = TraceBus::new();
+    let mut memory_final: Memory = Memory::new();
+    let mut cpu_ut = Cpu::new_test(
+        test.initial.pc as u16,
+        test.initial.s as u8,
+        test.initial.a as u8,
+        test.initial.x as u8,
+        test.initial.y as u8,
+        test.initial.p as u8,
+    );
+    cpu_ut.variant = variant;
+    for ram_value in &test.initial.ram {
+        bus.mem[ram_value[0] as u16] = ram_value[1] as u8;
+    }
+    let outcome = cpu_ut.run_instr(&mut bus);
+
+    let cpu_final = Cpu::new_test(
+        test.final_field.pc as u16,
+        test.final_field.s as u8,
+        test.final_field.a as u8,
+        test.final_field.x as u8,
+        test.final_field.y as u8,
+        test.final_field.p as u8,
+    );
+    for ram_final in &test.final_field.ram {
+        memory_final[ram_final[0] as u16] = ram_final[1] as u8;
+    }
+    if bus.mem != memory_final {
+        for ((ut, fin), idx) in bus.mem.mem.iter().zip(memory_final.mem.iter()).zip(0..0xFFFF) {
+            if *ut != *fin {
+                println!("Memory ut {:x?} != Memory final {:x?} @ addr {:x?}", *ut, *fin, idx);
             }
-            test_number += 1
         }
+        panic!("memory_ut != memory_final");
+    }
+    if cpu_final != cpu_ut {
+        println!("name:{:#x?}", test.name);
+        println!("cpu_ut {:#x?}", cpu_ut);
+        println!("cpu_final {:#x?}", cpu_final);
+        assert_eq!(cpu_final, cpu_ut, "cpu_final != cpu_ut");
+    }
+    // JAM, WAI and STP leave the bus idle where the vectors list the
+    // cycles of a chip left running, so only completed instructions are
+    // timed.
+    if outcome.map(|o| o.state) == Ok(RunState::Running) {
+        let expected: Vec<(u16, u8, &str)> =
+            test.cycles.iter().map(|(addr, val, kind)| (*addr as u16, *val as u8, kind.as_str())).collect();
+        assert_eq!(bus.accesses, expected, "{}", test.name);
+    }
+}
+
+#[test]
+fn single_step_vector() {
+    // the vector quoted above Cpu::ASL
+    let json = r#"{ "name": "0e 86 ef", "initial": { "pc": 254, "s": 226, "a": 7, "x": 99, "y": 166, "p": 239, "ram": [ [254, 14], [255, 134], [256, 239], [61318, 149], [257, 103]]}, "final": { "pc": 257, "s": 226, "a": 7, "x": 99, "y": 166, "p": 109, "ram": [ [254, 14], [255, 134], [256, 239], [257, 103], [61318, 42]]}, "cycles": [ [254, 14, "read"], [255, 134, "read"], [256, 239, "read"], [61318, 149, "read"], [61318, 149, "write"], [61318, 42, "write"]] }"#;
+    let test: Root2 = serde_json::from_str(json).unwrap();
+    run_vector(&test, Variant::Nmos6502);
+    // one cycle too many is caught
+    let mut slow = test.clone();
+    slow.cycles.pop();
+    assert!(std::panic::catch_unwind(|| run_vector(&slow, Variant::Nmos6502)).is_err());
+}
+
+// A few vectors per addressing mode, page cross, branch, stack and
+// interrupt class in the suite's format, so timing stays checked when the
+// full suites are not on disk.
+#[test]
+fn single_step_subset() {
+    let tests: Vec<Root2> = serde_json::from_str(include_str!("single_step.json")).unwrap();
+    for test in &tests {
+        run_vector(test, Variant::Nmos6502);
     }
 }
@@ -0,0 +1,72 @@
+use crate::Cpu;
+use core::ops::{Index, IndexMut};
+use std::fs;
+
+// NROM cartridge with 2 KiB of mirrored RAM. Everything else reads as open
+// bus and ignores writes.
+pub struct NesMemory {
+    pub ram: [u8; 0x0800],
+    pub rom: Vec<u8>,
+    pub open_bus: u8,
+}
+
+impl Index<u16> for NesMemory {
+    type Output = u8;
+    fn index(&self, index: u16) -> &Self::Output {
+        match index {
+            0x0000..=0x1FFF => &self.ram[index as usize & 0x07FF],
+            0x8000..=0xFFFF => &self.rom[(index as usize - 0x8000) % self.rom.len()],
+            _ => &self.open_bus,
+        }
+    }
+}
+
+impl IndexMut<u16> for NesMemory {
+    fn index_mut(&mut self, index: u16) -> &mut Self::Output {
+        match index {
+            0x0000..=0x1FFF => &mut self.ram[index as usize & 0x07FF],
+            _ => &mut self.open_bus,
+        }
+    }
+}
+
+// Value of `key:` in a Nintendulator log line, e.g. "A:00" or "CYC:7".
+fn field(line: &str, key: &str) -> u64 {
+    let start = line.find(key).unwrap() + key.len();
+    let value = line[start..].split_whitespace().next().unwrap();
+    let radix = if key == "CYC:" { 10 } else { 16 };
+    u64::from_str_radix(value, radix).unwrap()
+}
+
+// Runs nestest.nes in automation mode from $C000 and checks PC, the
+// registers and the cycle count before every instruction against
+// nestest.log.
+#[test]
+#[ignore = "needs nestest.nes and nestest.log in the crate root"]
+fn nestest() {
+    let rom = fs::read("nestest.nes").unwrap_or_else(|e| panic!("nestest.nes: {}", e));
+    let log = fs::read_to_string("nestest.log").unwrap_or_else(|e| panic!("nestest.log: {}", e));
+    let mut mem = NesMemory {
+        ram: [0; 0x0800],
+        rom: rom[0x0010..0x4010].to_vec(),
+        open_bus: 0,
+    };
+    let mut cpu = Cpu::new(None);
+    cpu.power_on(&mut mem);
+    cpu.pc = 0xC000;
+    cpu.s.set(0x24);
+    for line in log.lines() {
+        let state = (cpu.pc, cpu.a, cpu.x, cpu.y, cpu.s.get(), cpu.sp, cpu.cycles);
+        let expected = (
+            u16::from_str_radix(&line[0..4], 16).unwrap(),
+            field(line, "A:") as u8,
+            field(line, "X:") as u8,
+            field(line, "Y:") as u8,
+            field(line, "P:") as u8,
+            field(line, "SP:") as u8,
+            field(line, "CYC:"),
+        );
+        assert_eq!(state, expected, "{}", line);
+        cpu.run_instr(&mut mem).unwrap();
+    }
+}
@@ -0,0 +1,341 @@
+use super::{load_program, random_memory, Memory};
+use crate::{Cpu, CpuError, CpuState, RunState, Variant};
+
+pub fn run_one(program: &[u8], setup: impl Fn(&mut Cpu, &mut Memory)) -> (Cpu, Memory) {
+    let mut mem = Memory::new();
+    let mut cpu = load_program(&mut mem, program);
+    setup(&mut cpu, &mut mem);
+    cpu.run_instr(&mut mem).unwrap();
+    (cpu, mem)
+}
+
+#[test]
+fn undocumented_opcodes() {
+    // LAX zp
+    let (cpu, _) = run_one(&[0xA7, 0x10], |_, mem| mem.mem[0x10] = 0x80);
+    assert_eq!((cpu.a, cpu.x, cpu.s.get_negative()), (0x80, 0x80, true));
+    // SAX abs
+    let (_, mem) = run_one(&[0x8F, 0x00, 0x03], |cpu, _| {
+        cpu.a = 0xF0;
+        cpu.x = 0x3C;
+    });
+    assert_eq!(mem.mem[0x0300], 0x30);
+    // DCP zp: decrement then compare
+    let (cpu, mem) = run_one(&[0xC7, 0x10], |cpu, mem| {
+        cpu.a = 0x41;
+        mem.mem[0x10] = 0x42;
+    });
+    assert_eq!(mem.mem[0x10], 0x41);
+    assert!(cpu.s.get_zero() && cpu.s.get_carry());
+    // ISC zp: increment then subtract
+    let (cpu, _) = run_one(&[0xE7, 0x10], |cpu, mem| {
+        cpu.a = 0x10;
+        cpu.s.set_carry(true);
+        mem.mem[0x10] = 0x04;
+    });
+    assert_eq!(cpu.a, 0x0B);
+    // SBX #imm
+    let (cpu, _) = run_one(&[0xCB, 0x02], |cpu, _| {
+        cpu.a = 0x0F;
+        cpu.x = 0xFC;
+    });
+    assert_eq!(cpu.x, 0x0A);
+    assert!(cpu.s.get_carry());
+    // ARR #imm with carry in
+    let (cpu, _) = run_one(&[0x6B, 0xFF], |cpu, _| {
+        cpu.a = 0xC0;
+        cpu.s.set_carry(true);
+    });
+    assert_eq!(cpu.a, 0xE0);
+    assert!(cpu.s.get_carry() && !cpu.s.get_overflow());
+    // SHX abs,Y crossing a page stores to the corrupted high byte
+    let (_, mem) = run_one(&[0x9E, 0xF0, 0x12], |cpu, _| {
+        cpu.x = 0x05;
+        cpu.y = 0x20;
+    });
+    assert_eq!(mem.mem[0x0110], 0x05 & 0x13);
+}
+
+#[test]
+fn unstable_opcode_quirks() {
+    let xaa = |magic: u8| {
+        run_one(&[0x8B, 0xFF], |cpu, _| {
+            cpu.quirks.xaa_magic = magic;
+            cpu.a = 0x01;
+            cpu.x = 0xFF;
+        })
+        .0
+        .a
+    };
+    assert_eq!(xaa(0xEE), 0xEF);
+    assert_eq!(xaa(0xFF), 0xFF);
+    assert_eq!(xaa(0x00), 0x01);
+
+    let (cpu, _) = run_one(&[0xAB, 0x0F], |cpu, _| {
+        cpu.quirks.lxa_magic = 0x00;
+        cpu.a = 0x33;
+    });
+    assert_eq!((cpu.a, cpu.x), (0x03, 0x03));
+
+    // without the H+1 term the full register is stored at the carried address
+    let (_, mem) = run_one(&[0x9E, 0xF0, 0x12], |cpu, _| {
+        cpu.quirks.sh_and_high = false;
+        cpu.quirks.sh_corrupt_address = false;
+        cpu.x = 0x05;
+        cpu.y = 0x20;
+    });
+    assert_eq!(mem.mem[0x1310], 0x05);
+}
+
+fn decimal(variant: Variant, program: &[u8], a: u8, carry: bool) -> Cpu {
+    run_one(program, |cpu, _| {
+        cpu.variant = variant;
+        cpu.s.set_decimal(true);
+        cpu.s.set_carry(carry);
+        cpu.a = a;
+    })
+    .0
+}
+
+#[test]
+fn decimal_mode() {
+    let cpu = decimal(Variant::Nmos6502, &[0x69, 0x01], 0x99, false);
+    assert_eq!(cpu.a, 0x00);
+    assert!(cpu.s.get_carry());
+    // Z follows the binary sum, not the BCD result
+    assert!(!cpu.s.get_zero());
+
+    let cpu = decimal(Variant::Nmos6502, &[0x69, 0x27], 0x15, false);
+    assert_eq!(cpu.a, 0x42);
+
+    let cpu = decimal(Variant::Nmos6502, &[0xE9, 0x01], 0x00, true);
+    assert_eq!(cpu.a, 0x99);
+    assert!(!cpu.s.get_carry());
+
+    // ISC goes through the same decimal subtract
+    let (cpu, _) = run_one(&[0xE7, 0x10], |cpu, mem| {
+        cpu.variant = Variant::Nmos6502;
+        cpu.s.set_decimal(true);
+        cpu.s.set_carry(true);
+        cpu.a = 0x50;
+        mem.mem[0x10] = 0x08;
+    });
+    assert_eq!(cpu.a, 0x41);
+
+    let cpu = decimal(Variant::Nmos6502, &[0x6B, 0xFF], 0x99, false);
+    assert_eq!(cpu.a, 0xA2);
+    assert!(cpu.s.get_carry());
+
+    // the 2A03 keeps D as a plain flag
+    let cpu = decimal(Variant::Ricoh2A03, &[0x69, 0x01], 0x99, false);
+    assert_eq!(cpu.a, 0x9A);
+    assert!(!cpu.s.get_carry());
+}
+
+fn cmos(program: &[u8], setup: impl Fn(&mut Cpu, &mut Memory)) -> (Cpu, Memory) {
+    run_one(program, |cpu, mem| {
+        cpu.variant = Variant::Cmos65C02;
+        setup(cpu, mem);
+    })
+}
+
+#[test]
+fn cmos_instructions() {
+    // BRA
+    let (cpu, _) = cmos(&[0x80, 0x10], |_, _| {});
+    assert_eq!(cpu.pc, 0x0212);
+    // STZ abs,X
+    let (_, mem) = cmos(&[0x9E, 0x00, 0x03], |cpu, mem| {
+        cpu.x = 0x01;
+        mem.mem[0x0301] = 0xAA;
+    });
+    assert_eq!(mem.mem[0x0301], 0x00);
+    // TSB and TRB set Z from A & m before changing memory
+    let (cpu, mem) = cmos(&[0x04, 0x10], |cpu, mem| {
+        cpu.a = 0x0F;
+        mem.mem[0x10] = 0xF0;
+    });
+    assert_eq!((mem.mem[0x10], cpu.s.get_zero()), (0xFF, true));
+    let (cpu, mem) = cmos(&[0x1C, 0x00, 0x03], |cpu, mem| {
+        cpu.a = 0x0F;
+        mem.mem[0x0300] = 0xFF;
+    });
+    assert_eq!((mem.mem[0x0300], cpu.s.get_zero()), (0xF0, false));
+    // LDA (zp)
+    let (cpu, _) = cmos(&[0xB2, 0x10], |_, mem| {
+        mem.mem[0x10] = 0x34;
+        mem.mem[0x11] = 0x12;
+        mem.mem[0x1234] = 0x99;
+    });
+    assert_eq!(cpu.a, 0x99);
+    // BIT #imm only touches Z
+    let (cpu, _) = cmos(&[0x89, 0xC0], |cpu, _| cpu.a = 0x01);
+    assert!(cpu.s.get_zero() && !cpu.s.get_negative() && !cpu.s.get_overflow());
+    // INC A, DEC A
+    let (cpu, _) = cmos(&[0x1A], |cpu, _| cpu.a = 0xFF);
+    assert_eq!((cpu.a, cpu.s.get_zero()), (0x00, true));
+    let (cpu, _) = cmos(&[0x3A], |cpu, _| cpu.a = 0x00);
+    assert_eq!((cpu.a, cpu.s.get_negative()), (0xFF, true));
+    // PHX then PLY
+    let mut mem = Memory::new();
+    mem.mem[0x0200..0x0202].copy_from_slice(&[0xDA, 0x7A]);
+    let mut cpu = Cpu::new(Some(0x0200));
+    cpu.variant = Variant::Cmos65C02;
+    cpu.x = 0x80;
+    cpu.run_instr(&mut mem).unwrap();
+    cpu.run_instr(&mut mem).unwrap();
+    assert_eq!((cpu.y, cpu.s.get_negative()), (0x80, true));
+    // JMP (abs,X) and the fixed JMP ($xxFF)
+    let (cpu, _) = cmos(&[0x7C, 0x00, 0x03], |cpu, mem| {
+        cpu.x = 0x02;
+        mem.mem[0x0302] = 0x78;
+        mem.mem[0x0303] = 0x56;
+    });
+    assert_eq!(cpu.pc, 0x5678);
+    let (cpu, _) = cmos(&[0x6C, 0xFF, 0x03], |_, mem| {
+        mem.mem[0x03FF] = 0x78;
+        mem.mem[0x0400] = 0x56;
+        mem.mem[0x0300] = 0x12;
+    });
+    assert_eq!(cpu.pc, 0x5678);
+    // undefined opcodes are NOPs of the documented length
+    for (program, len) in [(&[0x03u8][..], 1), (&[0x02, 0x00], 2), (&[0x5C, 0x00, 0x00], 3), (&[0xFC, 0x00, 0x00], 3)] {
+        let (cpu, _) = cmos(program, |_, _| {});
+        assert_eq!(cpu.pc, 0x0200 + len, "opcode {:02X}", program[0]);
+    }
+    // BRK clears D
+    let (cpu, _) = cmos(&[0x00], |cpu, _| cpu.s.set_decimal(true));
+    assert!(!cpu.s.get_decimal());
+}
+
+#[test]
+fn cmos_decimal_mode() {
+    // N and Z follow the decimal result
+    let nmos = decimal(Variant::Nmos6502, &[0x69, 0x01], 0x99, false);
+    let cmos = decimal(Variant::Cmos65C02, &[0x69, 0x01], 0x99, false);
+    assert_eq!((nmos.a, nmos.s.get_zero(), nmos.s.get_negative()), (0x00, false, true));
+    assert_eq!((cmos.a, cmos.s.get_zero(), cmos.s.get_negative()), (0x00, true, false));
+    assert!(cmos.s.get_carry());
+    // SBC corrects a low-nibble borrow without touching the high nibble
+    let nmos = decimal(Variant::Nmos6502, &[0xE9, 0x0F], 0x20, true);
+    let cmos = decimal(Variant::Cmos65C02, &[0xE9, 0x0F], 0x20, true);
+    assert_eq!((nmos.a, cmos.a), (0x1B, 0x0B));
+    let cmos = decimal(Variant::Cmos65C02, &[0xE9, 0x01], 0x00, true);
+    assert_eq!((cmos.a, cmos.s.get_carry(), cmos.s.get_negative()), (0x99, false, true));
+}
+
+fn w65c02s(program: &[u8], setup: impl Fn(&mut Cpu, &mut Memory)) -> (Cpu, Memory) {
+    run_one(program, |cpu, mem| {
+        cpu.variant = Variant::W65C02S;
+        setup(cpu, mem);
+    })
+}
+
+#[test]
+fn w65c02s_bit_instructions() {
+    // RMB5, SMB2
+    let (_, mem) = w65c02s(&[0x57, 0x10], |_, mem| mem.mem[0x10] = 0xFF);
+    assert_eq!(mem.mem[0x10], 0xDF);
+    let (_, mem) = w65c02s(&[0xA7, 0x10], |_, _| {});
+    assert_eq!(mem.mem[0x10], 0x04);
+    // BBR3 falls through when the bit is set, BBS3 branches
+    let (cpu, _) = w65c02s(&[0x3F, 0x10, 0x20], |_, mem| mem.mem[0x10] = 0x08);
+    assert_eq!(cpu.pc, 0x0203);
+    let (cpu, _) = w65c02s(&[0xBF, 0x10, 0x20], |_, mem| mem.mem[0x10] = 0x08);
+    assert_eq!(cpu.pc, 0x0223);
+    // the plain 65C02 keeps these slots as one-byte NOPs
+    let (cpu, mem) = cmos(&[0x57, 0x10], |_, mem| mem.mem[0x10] = 0xFF);
+    assert_eq!((cpu.pc, mem.mem[0x10]), (0x0201, 0xFF));
+}
+
+#[test]
+fn wai_and_stp() {
+    let mut mem = Memory::new();
+    mem.mem[0x0200..0x0203].copy_from_slice(&[0xCB, 0xE8, 0xDB]);
+    let mut cpu = Cpu::new(Some(0x0200));
+    cpu.variant = Variant::W65C02S;
+    cpu.s.set_interrupt(true);
+    assert_eq!(cpu.run_instr(&mut mem).unwrap().state, RunState::Waiting);
+    // idle cycles neither touch the bus nor advance PC
+    assert_eq!(cpu.run_instr(&mut mem).unwrap().cycles, 1);
+    assert_eq!(cpu.pc, 0x0201);
+    // a masked IRQ wakes the CPU without taking the interrupt
+    cpu.set_irq_line(true);
+    assert_eq!(cpu.run_instr(&mut mem).unwrap().state, RunState::Running);
+    cpu.run_instr(&mut mem).unwrap();
+    assert_eq!((cpu.pc, cpu.x), (0x0202, 0x01));
+    let stopped = Err(CpuError::Halted { pc: 0x0202, opcode: 0xDB });
+    assert_eq!(cpu.run_instr(&mut mem), stopped);
+    cpu.set_nmi_line(true);
+    assert_eq!(cpu.run_instr(&mut mem), stopped);
+    assert_eq!(cpu.state, RunState::Stopped);
+    cpu.reset(&mut mem);
+    assert_eq!(cpu.state, RunState::Running);
+}
+
+#[test]
+fn jam_halts_until_reset() {
+    let mut mem = Memory::new();
+    mem.mem[0x0200..0x0202].copy_from_slice(&[0xEA, 0x02]);
+    mem.mem[0xFFFC..0xFFFE].copy_from_slice(&[0x00, 0x02]);
+    let mut cpu = Cpu::new(Some(0x0200));
+    cpu.run_instr(&mut mem).unwrap();
+    let halted = Err(CpuError::Halted { pc: 0x0201, opcode: 0x02 });
+    assert_eq!(cpu.run_instr(&mut mem), halted);
+    // interrupts do not wake it
+    cpu.set_nmi_line(true);
+    cpu.set_irq_line(true);
+    cpu.cycles = 0;
+    assert_eq!(cpu.run_instr(&mut mem), halted);
+    assert_eq!(cpu.cycles, 1);
+    let jammed = RunState::Jammed { addr: 0x0201, opcode: 0x02 };
+    assert_eq!((cpu.state, cpu.pc), (jammed, 0x0202));
+    cpu.reset(&mut mem);
+    assert_eq!((cpu.state, cpu.pc), (RunState::Running, 0x0200));
+}
+
+#[test]
+fn arbitrary_code_never_panics() {
+    let variants = [Variant::Ricoh2A03, Variant::Nmos6502, Variant::Cmos65C02, Variant::W65C02S];
+    let mut seed = 0x2545_F491u32;
+    for &variant in variants.iter() {
+        for opcode in 0..=0xFF {
+            let mut mem = random_memory(&mut seed);
+            mem.mem[0x0200] = opcode;
+            let mut cpu = Cpu::new(Some(0x0200));
+            cpu.variant = variant;
+            for _ in 0..64 {
+                if cpu.run_instr(&mut mem).is_err() {
+                    cpu.reset(&mut mem);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn corrupted_save_states_never_panic() {
+    let mut seed = 0x1B87_3593u32;
+    let mut mem = random_memory(&mut seed);
+    let noise = random_memory(&mut seed).mem;
+    let mut cpu = Cpu::new(Some(0x0200));
+    for n in 0..2000 {
+        cpu.tick(&mut mem);
+        if cpu.run_instr(&mut mem).is_err() {
+            cpu.reset(&mut mem);
+        }
+        // overwrite one byte past the header with noise
+        let mut bytes = cpu.save_state().to_bytes();
+        bytes[5 + n % (CpuState::SIZE - 5)] = noise[n];
+        if let Ok(state) = CpuState::from_bytes(&bytes) {
+            let mut copy = Cpu::new(None);
+            copy.load_state(&state).unwrap();
+            let mut scratch = mem;
+            for _ in 0..16 {
+                if copy.run_instr(&mut scratch).is_err() {
+                    copy.reset(&mut scratch);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use super::{setup, Memory};
+use crate::{Cpu, CpuState, Rewind};
+
+// start: LDX #$00; loop: INC $0300,X; DEC $0500,X; INX; BNE loop;
+// INC $10; JMP start
+const PROGRAM: [u8; 16] = [
+    0xA2, 0x00, 0xFE, 0x00, 0x03, 0xDE, 0x00, 0x05, 0xE8, 0xD0, 0xF7, 0xE6, 0x10, 0x4C, 0x00, 0x02,
+];
+
+// Runs for `cycles`, recording into `rewind` and returning what it kept.
+fn run(cpu: &mut Cpu, mem: &mut Memory, rewind: &mut Rewind, cycles: u64) -> Vec<(CpuState, Memory)> {
+    let mut captured = Vec::new();
+    let end = cpu.cycles + cycles;
+    while cpu.cycles < end {
+        cpu.run_instr(mem).unwrap();
+        if rewind.record(cpu, mem) {
+            captured.push((cpu.save_state(), *mem));
+        }
+    }
+    captured
+}
+
+#[test]
+fn rewinds_to_earlier_snapshots() {
+    let (mut cpu, mut mem) = setup(&PROGRAM);
+    let mut rewind = Rewind::new(1000, usize::MAX);
+    let captured = run(&mut cpu, &mut mem, &mut rewind, 20_000);
+    assert_eq!(rewind.len(), captured.len());
+
+    let target = cpu.cycles - 2500;
+    let went_back = rewind.rewind_cycles(&mut cpu, &mut mem, 2500).unwrap();
+    let (state, memory) = captured.iter().rev().find(|(s, _)| s.cycles() <= target).unwrap();
+    assert_eq!(cpu.save_state(), *state);
+    assert_eq!(mem, *memory);
+    assert_eq!(went_back, target + 2500 - state.cycles());
+
+    // stepping by frames and seconds lands on snapshots too
+    rewind.rewind_frames(&mut cpu, &mut mem, 3, 1000).unwrap();
+    let (state, memory) = captured.iter().find(|(s, _)| s.cycles() == cpu.cycles).unwrap();
+    assert_eq!((cpu.save_state(), mem), (*state, *memory));
+    rewind.rewind_seconds(&mut cpu, &mut mem, 0.002, 1_000_000).unwrap();
+    let (state, memory) = captured.iter().find(|(s, _)| s.cycles() == cpu.cycles).unwrap();
+    assert_eq!((cpu.save_state(), mem), (*state, *memory));
+
+    // the rewound timeline replays identically and records again
+    let recaptured = run(&mut cpu, &mut mem, &mut rewind, 5000);
+    assert!(recaptured.iter().all(|c| captured.contains(c)));
+
+    // asking for more than the buffer holds stops at the oldest
+    rewind.rewind_cycles(&mut cpu, &mut mem, u64::MAX).unwrap();
+    assert_eq!((cpu.save_state(), mem), captured[0]);
+    assert_eq!(rewind.len(), 1);
+}
+
+#[test]
+fn memory_limit_drops_the_oldest() {
+    let (mut cpu, mut mem) = setup(&PROGRAM);
+    let mut rewind = Rewind::new(500, 80 * 1024);
+    let captured = run(&mut cpu, &mut mem, &mut rewind, 200_000);
+    assert!(rewind.memory_usage() <= 80 * 1024);
+    // the deltas are small next to the full 64K image
+    assert!(rewind.len() > 10 && rewind.len() < captured.len());
+    let (oldest, newest) = rewind.span().unwrap();
+    assert_eq!(newest, captured.last().unwrap().0.cycles());
+    rewind.rewind_cycles(&mut cpu, &mut mem, u64::MAX).unwrap();
+    let (state, memory) = captured.iter().find(|(s, _)| s.cycles() == oldest).unwrap();
+    assert_eq!((cpu.save_state(), mem), (*state, *memory));
+
+    // a limit below one image still keeps the newest snapshot
+    let mut tiny = Rewind::new(500, 16);
+    run(&mut cpu, &mut mem, &mut tiny, 5000);
+    assert_eq!(tiny.len(), 1);
+}
@@ -0,0 +1,92 @@
+use super::cycles::TraceBus;
+use super::setup_traced;
+use crate::{Cpu, CpuState, StateError, Variant};
+
+// LDX #$00; loop: INC $10,X; LDA $0300,X; ADC #$03; STA $0400,X; INX;
+// BNE loop
+const PROGRAM: [u8; 15] = [
+    0xA2, 0x00, 0xF6, 0x10, 0xBD, 0x00, 0x03, 0x69, 0x03, 0x9D, 0x00, 0x04, 0xE8, 0xD0, 0xF3,
+];
+
+#[test]
+fn restored_state_resumes_bit_identically() {
+    for variant in [Variant::Nmos6502, Variant::W65C02S] {
+        for save_at in 0..80 {
+            let (mut cpu, mut bus) = setup_traced(&PROGRAM);
+            cpu.variant = variant;
+            for n in 0..save_at {
+                // leave interrupts latched and in progress at the save
+                match n {
+                    30 => cpu.set_nmi_line(true),
+                    45 => assert!(cpu.assert_irq(3)),
+                    _ => {}
+                }
+                cpu.tick(&mut bus);
+            }
+            let bytes = cpu.save_state().to_bytes();
+            let mut restored = Cpu::new(None);
+            restored.load_state(&CpuState::from_bytes(&bytes).unwrap()).unwrap();
+            assert_eq!(restored.save_state(), cpu.save_state());
+
+            let mut copy = TraceBus::new();
+            copy.mem = bus.mem;
+            bus.accesses.clear();
+            for _ in 0..400 {
+                cpu.tick(&mut bus);
+                restored.tick(&mut copy);
+            }
+            assert_eq!(copy.accesses, bus.accesses, "{:?} saved after {} cycles", variant, save_at);
+            assert_eq!(restored.save_state(), cpu.save_state());
+            assert_eq!(restored.cycles, cpu.cycles);
+        }
+    }
+}
+
+#[test]
+fn rejects_bad_state_data() {
+    let (cpu, _) = setup_traced(&PROGRAM);
+    let bytes = cpu.save_state().to_bytes();
+    assert_eq!(CpuState::from_bytes(&bytes[..10]), Err(StateError::Truncated));
+    let mut bad = bytes;
+    bad[0] = b'X';
+    assert_eq!(CpuState::from_bytes(&bad), Err(StateError::BadMagic));
+    let mut bad = bytes;
+    bad[4] = 9;
+    assert_eq!(CpuState::from_bytes(&bad), Err(StateError::UnsupportedVersion(9)));
+    // the variant byte
+    let mut bad = bytes;
+    bad[27] = 7;
+    assert_eq!(CpuState::from_bytes(&bad), Err(StateError::Corrupt));
+    // a step past the end of NOP's sequence
+    let mut bad = bytes;
+    bad[23] = 255;
+    assert_eq!(CpuState::from_bytes(&bad), Err(StateError::Corrupt));
+    bad[23] = 2;
+    assert_eq!(CpuState::from_bytes(&bad), Err(StateError::Corrupt));
+    // an IRQ being taken by something other than the BRK sequence
+    let mut bad = bytes;
+    bad[40] = 1;
+    assert_eq!(CpuState::from_bytes(&bad), Err(StateError::Corrupt));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_round_trip() {
+    let (mut cpu, mut bus) = setup_traced(&PROGRAM);
+    cpu.variant = Variant::Cmos65C02;
+    for _ in 0..13 {
+        cpu.tick(&mut bus);
+    }
+    let state = cpu.save_state();
+    let json = serde_json::to_string(&state).unwrap();
+    assert_eq!(serde_json::from_str::<CpuState>(&json).unwrap(), state);
+
+    // deserializing does not check the state, loading it does
+    let (mut cpu, _) = setup_traced(&PROGRAM);
+    cpu.variant = Variant::Cmos65C02;
+    let json = serde_json::to_string(&cpu.save_state()).unwrap();
+    let bad: CpuState = serde_json::from_str(&json.replace("\"step\":0", "\"step\":255")).unwrap();
+    let before = cpu.save_state();
+    assert_eq!(cpu.load_state(&bad), Err(StateError::Corrupt));
+    assert_eq!(cpu.save_state(), before);
+}
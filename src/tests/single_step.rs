@@ -1,5 +1,4 @@
 use serde::{Deserialize, Serialize};
-pub type Root = Vec<Root2>;
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -0,0 +1,70 @@
+use super::{setup, Memory};
+use crate::{CpuState, Stop, TimeTravel};
+
+// start: LDX #$00; loop: INC $0300,X; LDA $0300,X; STA $10; INX; BNE
+// loop; INC $11; JMP start. $10 is rewritten on every pass and $11 once
+// per 256 of them.
+const PROGRAM: [u8; 18] = [
+    0xA2, 0x00, 0xFE, 0x00, 0x03, 0xBD, 0x00, 0x03, 0x85, 0x10, 0xE8, 0xD0, 0xF5, 0xE6, 0x11, 0x4C, 0x00, 0x02,
+];
+
+#[test]
+fn reverse_step_undoes_each_instruction() {
+    let (mut cpu, mut mem) = setup(&PROGRAM);
+    let mut history = TimeTravel::new(usize::MAX);
+    let mut states: Vec<(CpuState, Memory)> = Vec::new();
+    for _ in 0..3000 {
+        states.push((cpu.save_state(), mem));
+        history.step(&mut cpu, &mut mem).unwrap();
+    }
+    while let Some(expected) = states.pop() {
+        assert!(history.reverse_step(&mut cpu, &mut mem));
+        assert_eq!((cpu.save_state(), mem), expected);
+    }
+    assert!(!history.reverse_step(&mut cpu, &mut mem));
+}
+
+#[test]
+fn reverse_continue_to_breakpoints_and_watchpoints() {
+    let (mut cpu, mut mem) = setup(&PROGRAM);
+    let mut history = TimeTravel::new(usize::MAX);
+    for _ in 0..5000 {
+        history.step(&mut cpu, &mut mem).unwrap();
+    }
+    // back to the last INC $11, before it ran
+    history.set_watchpoint(0x0011);
+    assert_eq!(history.reverse_continue(&mut cpu, &mut mem), Stop::Watchpoint { pc: 0x020D, addr: 0x0011 });
+    let value = mem.mem[0x11];
+    history.step(&mut cpu, &mut mem).unwrap();
+    assert_eq!(mem.mem[0x11], value + 1);
+    history.reverse_step(&mut cpu, &mut mem);
+    history.clear_watchpoint(0x0011);
+
+    // breakpoints stop before the instruction, and the current one is
+    // skipped so repeated calls walk back one loop pass at a time
+    history.set_breakpoint(0x0208);
+    assert_eq!(history.reverse_continue(&mut cpu, &mut mem), Stop::Breakpoint { pc: 0x0208 });
+    let x = cpu.x;
+    assert_eq!(history.reverse_continue(&mut cpu, &mut mem), Stop::Breakpoint { pc: 0x0208 });
+    assert_eq!(cpu.x, x.wrapping_sub(1));
+    history.clear_breakpoint(0x0208);
+    assert_eq!(history.reverse_continue(&mut cpu, &mut mem), Stop::Start);
+    assert_eq!((cpu.pc, cpu.cycles), (0x0200, 0));
+    assert_eq!(mem, setup(&PROGRAM).1);
+}
+
+#[test]
+fn history_limit_forgets_the_oldest() {
+    let (mut cpu, mut mem) = setup(&PROGRAM);
+    let mut history = TimeTravel::new(100);
+    for _ in 0..1000 {
+        history.step(&mut cpu, &mut mem).unwrap();
+    }
+    assert_eq!(history.len(), 100);
+    let mut check = setup(&PROGRAM);
+    for _ in 0..900 {
+        check.0.run_instr(&mut check.1).unwrap();
+    }
+    assert_eq!(history.reverse_continue(&mut cpu, &mut mem), Stop::Start);
+    assert_eq!((cpu.save_state(), mem), (check.0.save_state(), check.1));
+}
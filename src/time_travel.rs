@@ -0,0 +1,146 @@
+use crate::cpu::{Bus, Cpu, CpuError, CpuState, DebugBus, StepOutcome};
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+
+/// Why `TimeTravel::reverse_continue` stopped.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Stop {
+    /// The CPU is about to run the instruction at a breakpoint.
+    Breakpoint { pc: u16 },
+    /// The CPU is about to run the instruction that wrote `addr`.
+    Watchpoint { pc: u16, addr: u16 },
+    /// The oldest recorded instruction was undone.
+    Start,
+}
+
+// One recorded instruction: the CPU before it and how many of the logged
+// writes it made.
+struct Step {
+    cpu: CpuState,
+    writes: usize,
+}
+
+/// Instruction history that lets a debugger step backwards.
+///
+/// Instructions run through `step` save the CPU state before them and log
+/// every byte they write with the value it replaced, so `reverse_step` can
+/// put both back. The oldest instructions are forgotten past `limit`.
+pub struct TimeTravel {
+    limit: usize,
+    steps: VecDeque<Step>,
+    // (address, replaced value) of every write, oldest first
+    writes: VecDeque<(u16, u8)>,
+    breakpoints: Vec<u16>,
+    watchpoints: Vec<u16>,
+}
+
+// Logs writes on their way to the bus.
+struct Logged<'a, B: ?Sized> {
+    bus: &'a mut B,
+    writes: &'a mut VecDeque<(u16, u8)>,
+}
+
+impl<B: DebugBus + ?Sized> Bus for Logged<'_, B> {
+    fn read(&mut self, addr: u16) -> u8 {
+        self.bus.read(addr)
+    }
+    fn write(&mut self, addr: u16, val: u8) {
+        self.writes.push_back((addr, self.bus.peek(addr)));
+        self.bus.write(addr, val);
+    }
+}
+
+impl TimeTravel {
+    /// A history of at most `limit` instructions.
+    pub fn new(limit: usize) -> TimeTravel {
+        TimeTravel {
+            limit,
+            steps: VecDeque::new(),
+            writes: VecDeque::new(),
+            breakpoints: Vec::new(),
+            watchpoints: Vec::new(),
+        }
+    }
+    /// Instructions that can be undone.
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+    pub fn clear(&mut self) {
+        self.steps.clear();
+        self.writes.clear();
+    }
+    pub fn set_breakpoint(&mut self, pc: u16) {
+        if !self.breakpoints.contains(&pc) {
+            self.breakpoints.push(pc);
+        }
+    }
+    pub fn clear_breakpoint(&mut self, pc: u16) {
+        self.breakpoints.retain(|&b| b != pc);
+    }
+    /// Stops `reverse_continue` at instructions that write `addr`.
+    pub fn set_watchpoint(&mut self, addr: u16) {
+        if !self.watchpoints.contains(&addr) {
+            self.watchpoints.push(addr);
+        }
+    }
+    pub fn clear_watchpoint(&mut self, addr: u16) {
+        self.watchpoints.retain(|&w| w != addr);
+    }
+    /// Runs one instruction like `Cpu::run_instr` and records it.
+    pub fn step<B: DebugBus + ?Sized>(&mut self, cpu: &mut Cpu, bus: &mut B) -> Result<StepOutcome, CpuError> {
+        let state = cpu.save_state();
+        let before = self.writes.len();
+        let result = cpu.run_instr(&mut Logged {
+            bus,
+            writes: &mut self.writes,
+        });
+        self.steps.push_back(Step {
+            cpu: state,
+            writes: self.writes.len() - before,
+        });
+        if self.steps.len() > self.limit {
+            if let Some(oldest) = self.steps.pop_front() {
+                self.writes.drain(..oldest.writes);
+            }
+        }
+        result
+    }
+    /// Undoes the last recorded instruction. Returns false when there is
+    /// nothing left to undo.
+    pub fn reverse_step<B: DebugBus + ?Sized>(&mut self, cpu: &mut Cpu, bus: &mut B) -> bool {
+        self.undo(cpu, bus).is_some()
+    }
+    /// Undoes instructions until the CPU is about to run one at a
+    /// breakpoint or one that writes a watched address. The instruction
+    /// currently at a breakpoint does not count, so repeated calls move
+    /// from hit to hit.
+    pub fn reverse_continue<B: DebugBus + ?Sized>(&mut self, cpu: &mut Cpu, bus: &mut B) -> Stop {
+        while let Some(addr) = self.undo(cpu, bus) {
+            if let Some(addr) = addr {
+                return Stop::Watchpoint { pc: cpu.pc, addr };
+            }
+            if self.breakpoints.contains(&cpu.pc) {
+                return Stop::Breakpoint { pc: cpu.pc };
+            }
+        }
+        Stop::Start
+    }
+    // Undoes the last instruction and reports the first watched address it
+    // wrote, or None if the history is empty.
+    fn undo<B: DebugBus + ?Sized>(&mut self, cpu: &mut Cpu, bus: &mut B) -> Option<Option<u16>> {
+        let step = self.steps.pop_back()?;
+        let mut watched = None;
+        for _ in 0..step.writes {
+            let (addr, old) = self.writes.pop_back()?;
+            if self.watchpoints.contains(&addr) {
+                watched = Some(addr);
+            }
+            bus.poke(addr, old);
+        }
+        cpu.load_state(&step.cpu).ok()?;
+        Some(watched)
+    }
+}